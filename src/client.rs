@@ -0,0 +1,226 @@
+use std::sync::OnceLock;
+
+use async_lock::{Mutex, OnceCell};
+use zbus::fdo::NameOwnerChangedStream;
+use zbus::names::{OwnedUniqueName, WellKnownName};
+use zbus::{CacheProperties, Connection};
+
+use crate::handlers::easy_gnome::battery::UPowerProxy;
+use crate::handlers::easy_gnome::keyboard_backlight::{
+    Backend as KeyboardBacklightBackend, KbdBacklightProxy, KeyboardProxy,
+};
+use crate::handlers::easy_gnome::power::PowerProfilesService;
+use crate::handlers::easy_gnome::screencast::Recording;
+use crate::handlers::easy_gnome::{
+    ExtensionsProxy, PowerManagementProxy, PowerProfilesProxy, ScreenProxy, ScreencastProxy,
+    ScreenshotProxy,
+};
+use crate::Result;
+
+static SHARED_CLIENT: OnceLock<GnomeClient> = OnceLock::new();
+
+/// ## Client holding the session and system bus connections
+/// Each bus is opened once, on first use, and every proxy is built once and then reused.
+/// Proxies do not cache properties, reads always return the current value.
+/// The operations of the `easy_gnome` modules are available as methods, their free
+/// functions are shortcuts to the same methods on [`GnomeClient::shared`].
+///
+/// ```rust,no_run
+/// use gnome_dbus_api::GnomeClient;
+/// use gnome_dbus_api::handlers::easy_gnome::screen::Brightness;
+///
+/// async fn slider(values: Vec<i32>) -> gnome_dbus_api::Result<()> {
+///     let client = GnomeClient::new();
+///     for value in values {
+///         client.set_brightness(Brightness::new(value)?).await?;
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct GnomeClient {
+    session: OnceCell<Connection>,
+    system: OnceCell<Connection>,
+    power_management: OnceCell<PowerManagementProxy<'static>>,
+    power_profiles: OnceCell<PowerProfilesProxy<'static>>,
+    pub(crate) power_profiles_service: OnceCell<PowerProfilesService>,
+    screen: OnceCell<ScreenProxy<'static>>,
+    screenshot: OnceCell<ScreenshotProxy<'static>>,
+    screencast: OnceCell<ScreencastProxy<'static>>,
+    extensions: OnceCell<ExtensionsProxy<'static>>,
+    keyboard: OnceCell<KeyboardProxy<'static>>,
+    kbd_backlight: OnceCell<KbdBacklightProxy<'static>>,
+    upower: OnceCell<UPowerProxy<'static>>,
+    /// Detected backend, with the owner changes of gnome-settings-daemon since then
+    pub(crate) keyboard_backend:
+        Mutex<Option<(KeyboardBacklightBackend, NameOwnerChangedStream<'static>)>>,
+    /// Screencast started by this client
+    pub(crate) recording: std::sync::Mutex<Option<Recording>>,
+}
+
+impl GnomeClient {
+    pub fn new() -> GnomeClient {
+        GnomeClient::default()
+    }
+    /// ## Client using already opened connections
+    pub fn with_connections(session: Connection, system: Connection) -> GnomeClient {
+        GnomeClient {
+            session: OnceCell::from(session),
+            system: OnceCell::from(system),
+            ..GnomeClient::default()
+        }
+    }
+    /// ## Client used by the free functions
+    pub fn shared() -> &'static GnomeClient {
+        SHARED_CLIENT.get_or_init(GnomeClient::new)
+    }
+
+    /// ## Make the free functions use `client`, before any of them runs
+    #[cfg(test)]
+    pub(crate) fn set_shared(client: GnomeClient) {
+        if SHARED_CLIENT.set(client).is_err() {
+            panic!("The shared client is already in use");
+        }
+    }
+
+    pub async fn session(&self) -> Result<&Connection> {
+        Ok(self
+            .session
+            .get_or_try_init(|| async { Connection::session().await })
+            .await?)
+    }
+    pub async fn system(&self) -> Result<&Connection> {
+        Ok(self
+            .system
+            .get_or_try_init(|| async { Connection::system().await })
+            .await?)
+    }
+
+    pub(crate) async fn power_management_proxy(&self) -> Result<&PowerManagementProxy<'static>> {
+        let connection = self.system().await?;
+        Ok(self
+            .power_management
+            .get_or_try_init(|| {
+                PowerManagementProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn power_profiles_proxy(&self) -> Result<&PowerProfilesProxy<'static>> {
+        let service = self.power_profiles_service().await?;
+        let connection = self.system().await?;
+        self.power_profiles
+            .get_or_try_init(|| async {
+                Ok::<_, crate::Error>(
+                    service
+                        .proxy_builder(connection)?
+                        .cache_properties(CacheProperties::No)
+                        .build()
+                        .await?,
+                )
+            })
+            .await
+    }
+    pub(crate) async fn screen_proxy(&self) -> Result<&ScreenProxy<'static>> {
+        let connection = self.session().await?;
+        Ok(self
+            .screen
+            .get_or_try_init(|| {
+                ScreenProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn screenshot_proxy(&self) -> Result<&ScreenshotProxy<'static>> {
+        let connection = self.session().await?;
+        Ok(self
+            .screenshot
+            .get_or_try_init(|| {
+                ScreenshotProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn screencast_proxy(&self) -> Result<&ScreencastProxy<'static>> {
+        let connection = self.session().await?;
+        Ok(self
+            .screencast
+            .get_or_try_init(|| {
+                ScreencastProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn keyboard_proxy(&self) -> Result<&KeyboardProxy<'static>> {
+        let connection = self.session().await?;
+        Ok(self
+            .keyboard
+            .get_or_try_init(|| {
+                KeyboardProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn kbd_backlight_proxy(&self) -> Result<&KbdBacklightProxy<'static>> {
+        let connection = self.system().await?;
+        Ok(self
+            .kbd_backlight
+            .get_or_try_init(|| {
+                KbdBacklightProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn upower_proxy(&self) -> Result<&UPowerProxy<'static>> {
+        let connection = self.system().await?;
+        Ok(self
+            .upower
+            .get_or_try_init(|| {
+                UPowerProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+    pub(crate) async fn extensions_proxy(&self) -> Result<&ExtensionsProxy<'static>> {
+        let connection = self.session().await?;
+        Ok(self
+            .extensions
+            .get_or_try_init(|| {
+                ExtensionsProxy::builder(connection)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+            })
+            .await?)
+    }
+}
+
+/// ## Whether a well-known name is owned on the bus of the connection
+pub(crate) async fn name_has_owner(connection: &Connection, name: &'static str) -> Result<bool> {
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    Ok(dbus
+        .name_has_owner(WellKnownName::from_static_str_unchecked(name).into())
+        .await?)
+}
+
+/// ## Unique name owning a well-known name, `None` when nobody owns it
+pub(crate) async fn name_owner(
+    connection: &Connection,
+    name: &'static str,
+) -> Result<Option<OwnedUniqueName>> {
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    match dbus
+        .get_name_owner(WellKnownName::from_static_str_unchecked(name).into())
+        .await
+    {
+        Ok(owner) => Ok(Some(owner)),
+        Err(zbus::fdo::Error::NameHasNoOwner(_)) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -1,36 +1,440 @@
-use std::process::{Command, Output};
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard, OnceLock, RwLock, RwLockReadGuard};
+use std::task::{Context, Poll};
 
-pub fn set(key: &str, prop: &str, value: &str) -> Result<(), String> {
-    let mut cmd = Command::new("gsettings");
-    cmd.args(&["set", key, prop, value]);
-    match cmd.output() {
-        Ok(_) => Ok(()),
-        Err(_) => Err("Unable to set key".to_string()),
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_util::{Stream, StreamExt};
+pub use gio::glib::{FromVariant, StaticVariantType, ToVariant, Variant};
+use gio::glib::{MainContext, MainLoop};
+use gio::prelude::*;
+use gio::{Settings, SettingsBackend, SettingsSchema, SettingsSchemaKey, SettingsSchemaSource};
+
+use crate::{Error, Result};
+
+/// ## Where settings are stored
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    /// The backend picked by GIO, dconf on a GNOME desktop
+    Default,
+    /// Settings kept in memory, lost when the process exits
+    Memory,
+    /// Settings kept in a keyfile, created if missing
+    Keyfile(PathBuf),
+}
+
+struct Config {
+    backend: Backend,
+    schema_dir: Option<PathBuf>,
+}
+
+static CONFIG: RwLock<Config> = RwLock::new(Config {
+    backend: Backend::Default,
+    schema_dir: None,
+});
+
+/// The memory backend shared by every settings object of the process,
+/// so a value written by one is read by the others (watch threads included)
+struct MemoryBackend(SettingsBackend);
+
+// SAFETY: GObject reference counting is atomic. The memory backend keeps its values
+// in a GHashTable without any lock, every read and write of a value in this module
+// holds `ACCESS` so the table is never used by two threads at once.
+unsafe impl Send for MemoryBackend {}
+unsafe impl Sync for MemoryBackend {}
+
+static MEMORY_BACKEND: OnceLock<MemoryBackend> = OnceLock::new();
+
+static ACCESS: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static HOLDS_ACCESS: Cell<bool> = Cell::new(false);
+}
+
+struct Access {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Drop for Access {
+    fn drop(&mut self) {
+        HOLDS_ACCESS.with(|holds| holds.set(false));
     }
 }
-/// ## Get the value of a key
-pub fn get(key: &str, prop: &str) -> Result<String, String> {
-    let mut cmd = Command::new("gsettings");
-    cmd.args(&["get", key, prop]);
-    match cmd.output() {
-        Ok(Output { stdout, .. }) => {
-            let stdout_string = String::from_utf8(stdout).unwrap().replace("\n", "");
 
-            let parts = stdout_string.split(" ").collect::<Vec<&str>>();
+/// Held while reading or writing a value, see `MemoryBackend`.
+/// GLib may run a `changed` handler within the write that emitted it,
+/// the thread already holding the lock then goes on without taking it again.
+fn access() -> Option<Access> {
+    if HOLDS_ACCESS.with(Cell::get) {
+        return None;
+    }
+    let guard = ACCESS.lock().unwrap_or_else(|error| error.into_inner());
+    HOLDS_ACCESS.with(|holds| holds.set(true));
+    Some(Access { _guard: guard })
+}
+
+/// ## Choose the settings backend and an extra schema directory
+/// Meant for tests and sandboxes, call it before any other function of this module.
+/// Schemas compiled in `schema_dir` (with `glib-compile-schemas`) are looked up first,
+/// then the installed ones.
+pub fn configure(backend: Backend, schema_dir: Option<PathBuf>) {
+    let mut config = CONFIG.write().unwrap_or_else(|error| error.into_inner());
+    *config = Config {
+        backend,
+        schema_dir,
+    };
+}
 
-            if parts.len() > 1 {
-                return Ok(parts[1].trim().to_string());
-            }
-            Ok(stdout_string)
+fn config() -> RwLockReadGuard<'static, Config> {
+    CONFIG.read().unwrap_or_else(|error| error.into_inner())
+}
+
+pub(crate) fn schema_source() -> Result<SettingsSchemaSource> {
+    let default_source = SettingsSchemaSource::default();
+    match &config().schema_dir {
+        Some(schema_dir) => {
+            SettingsSchemaSource::from_directory(schema_dir, default_source.as_ref(), false)
+                .map_err(|error| {
+                    Error::InvalidValue(format!("{}: {}", schema_dir.display(), error))
+                })
+        }
+        None => default_source
+            .ok_or_else(|| Error::ServiceUnavailable("No GSettings schemas installed".to_string())),
+    }
+}
+
+/// ## Find a schema
+/// `schema` accepts the same `SCHEMA[:PATH]` syntax as the gsettings binary,
+/// the path is only needed by relocatable schemas.
+pub(crate) fn lookup(schema: &str) -> Result<(SettingsSchema, Option<&str>)> {
+    let (schema_id, path) = match schema.split_once(':') {
+        Some((schema_id, path)) => (schema_id, Some(path)),
+        None => (schema, None),
+    };
+    let settings_schema = schema_source()?
+        .lookup(schema_id, true)
+        .ok_or_else(|| Error::InvalidValue(format!("No such schema \"{}\"", schema_id)))?;
+    Ok((settings_schema, path))
+}
+
+/// ## Create the settings object of a schema
+pub(crate) fn settings(
+    settings_schema: &SettingsSchema,
+    schema_id: &str,
+    path: Option<&str>,
+) -> Result<Settings> {
+    // GIO aborts the process on a malformed path or a path given to a fixed schema
+    match (settings_schema.path(), path) {
+        (None, None) => {
+            return Err(Error::InvalidValue(format!(
+                "Schema \"{}\" is relocatable, use \"{}:/path/\"",
+                schema_id, schema_id
+            )))
+        }
+        (Some(_), Some(_)) => {
+            return Err(Error::InvalidValue(format!(
+                "Schema \"{}\" is not relocatable, it takes no path",
+                schema_id
+            )))
         }
-        Err(_) => Err("Unable to get key".to_string()),
+        (None, Some(path))
+            if !path.starts_with('/') || !path.ends_with('/') || path.contains("//") =>
+        {
+            return Err(Error::InvalidValue(format!(
+                "Path \"{}\" must start and end with \"/\" and contain no \"//\"",
+                path
+            )))
+        }
+        _ => {}
     }
+    let backend = match &config().backend {
+        Backend::Keyfile(file) => Some(gio::keyfile_settings_backend_new(
+            &file.to_string_lossy(),
+            "/",
+            None,
+        )),
+        Backend::Memory => Some(
+            MEMORY_BACKEND
+                .get_or_init(|| MemoryBackend(gio::memory_settings_backend_new()))
+                .0
+                .clone(),
+        ),
+        Backend::Default => None,
+    };
+    Ok(Settings::new_full(settings_schema, backend.as_ref(), path))
 }
-pub fn reset(key: &str, prop: &str) -> Result<(), String> {
-    let mut cmd = Command::new("gsettings");
-    cmd.args(&["reset", key, prop]);
-    match cmd.output() {
-        Ok(_) => Ok(()),
-        Err(_) => Err("Unable to reset key".to_string()),
+
+/// ## Open the settings holding a key
+fn open(schema: &str, key: &str) -> Result<(Settings, SettingsSchemaKey)> {
+    let (settings_schema, path) = lookup(schema)?;
+    if !settings_schema.has_key(key) {
+        return Err(Error::InvalidValue(format!(
+            "No such key \"{}\" in schema \"{}\"",
+            key, schema
+        )));
+    }
+    let settings = settings(&settings_schema, schema, path)?;
+    Ok((settings, settings_schema.key(key)))
+}
+
+/// ## Write a value, checking it against the key type and range
+fn write(settings: &Settings, schema_key: &SettingsSchemaKey, value: &Variant) -> Result<()> {
+    let key = schema_key.name();
+    if !schema_key.range_check(value) {
+        return Err(Error::InvalidValue(format!(
+            "{} is out of range for key \"{}\"",
+            value.print(true),
+            key
+        )));
+    }
+    if !settings.is_writable(&key) {
+        return Err(Error::AccessDenied(format!(
+            "Key \"{}\" is not writable",
+            key
+        )));
     }
+    let _access = access();
+    settings
+        .set_value(&key, value)
+        .map_err(|error| Error::Process(error.to_string()))?;
+    Settings::sync();
+    Ok(())
+}
+
+/// ## Set the value of a key from its text form
+/// `value` uses the GVariant text format, like `gsettings set`.
+/// Unquoted text is accepted for string keys.
+pub fn set_text(schema: &str, key: &str, value: &str) -> Result<()> {
+    let (settings, schema_key) = open(schema, key)?;
+    let value_type = schema_key.value_type();
+    let variant = match Variant::parse(Some(&*value_type), value) {
+        Ok(variant) => variant,
+        Err(_) if value_type.as_str() == "s" => value.to_variant(),
+        Err(error) => {
+            return Err(Error::InvalidValue(format!(
+                "\"{}\" is not a valid {} for key \"{}\": {}",
+                value,
+                value_type.as_str(),
+                key,
+                error
+            )))
+        }
+    };
+    write(&settings, &schema_key, &variant)
+}
+/// ## Get the value of a key in its text form
+/// Strings are returned without quotes, any other type in the GVariant text format.
+pub fn get_text(schema: &str, key: &str) -> Result<String> {
+    let value = get_value(schema, key)?;
+    match value.str() {
+        Some(text) => Ok(text.to_string()),
+        None => Ok(value.print(false).to_string()),
+    }
+}
+
+/// ## Set the value of a key
+/// The variant type must match the type declared by the schema.
+pub fn set_value(schema: &str, key: &str, value: &Variant) -> Result<()> {
+    let (settings, schema_key) = open(schema, key)?;
+    let value_type = schema_key.value_type();
+    if value.type_() != &*value_type {
+        return Err(Error::InvalidValue(format!(
+            "Key \"{}\" expects {}, got {}",
+            key,
+            value_type.as_str(),
+            value.type_().as_str()
+        )));
+    }
+    write(&settings, &schema_key, value)
+}
+/// ## Get the value of a key
+pub fn get_value(schema: &str, key: &str) -> Result<Variant> {
+    let (settings, _) = open(schema, key)?;
+    let _access = access();
+    Ok(settings.value(key))
+}
+
+/// ## Set a typed value
+/// ```rust,no_run
+/// use gnome_dbus_api::dconf;
+///
+/// dconf::set("org.gnome.desktop.peripherals.keyboard", "delay", 500u32)?;
+/// let options = vec!["caps:escape".to_string()];
+/// dconf::set("org.gnome.desktop.input-sources", "xkb-options", options)?;
+/// # Ok::<(), gnome_dbus_api::Error>(())
+/// ```
+pub fn set<T: ToVariant>(schema: &str, key: &str, value: T) -> Result<()> {
+    set_value(schema, key, &value.to_variant())
+}
+/// ## Get a typed value
+/// ```rust,no_run
+/// use gnome_dbus_api::dconf;
+///
+/// let delay: u32 = dconf::get("org.gnome.desktop.peripherals.keyboard", "delay")?;
+/// let sources: Vec<(String, String)> =
+///     dconf::get("org.gnome.desktop.input-sources", "sources")?;
+/// # Ok::<(), gnome_dbus_api::Error>(())
+/// ```
+pub fn get<T: FromVariant>(schema: &str, key: &str) -> Result<T> {
+    let value = get_value(schema, key)?;
+    value.get::<T>().ok_or_else(|| {
+        Error::Parse(format!(
+            "Key \"{}\" holds {}, not {}",
+            key,
+            value.type_().as_str(),
+            T::static_variant_type().as_str()
+        ))
+    })
+}
+pub fn reset(schema: &str, key: &str) -> Result<()> {
+    let (settings, _) = open(schema, key)?;
+    if !settings.is_writable(key) {
+        return Err(Error::AccessDenied(format!(
+            "Key \"{}\" is not writable",
+            key
+        )));
+    }
+    let _access = access();
+    settings.reset(key);
+    Settings::sync();
+    Ok(())
+}
+
+/// ## Stream of changes of a key or of a whole schema
+/// Driven by the GSettings `changed` signal on a dedicated GLib main loop,
+/// dropping the stream stops that loop.
+pub struct Watch<T> {
+    receiver: UnboundedReceiver<T>,
+    main_loop: MainLoop,
+}
+
+impl<T> Stream for Watch<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+impl<T> Drop for Watch<T> {
+    fn drop(&mut self) {
+        self.main_loop.quit();
+    }
+}
+
+/// ## Watch a key
+/// Yields the new value every time the key changes, whoever changed it.
+/// ```rust,no_run
+/// use futures_util::StreamExt;
+/// use gnome_dbus_api::dconf;
+///
+/// async fn follow_night_light() -> gnome_dbus_api::Result<()> {
+///     let mut changes =
+///         dconf::watch::<bool>("org.gnome.settings-daemon.plugins.color", "night-light-enabled")?;
+///     while let Some(enabled) = changes.next().await {
+///         println!("night light enabled: {}", enabled);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn watch<T>(schema: &str, key: &str) -> Result<Watch<T>>
+where
+    T: FromVariant + Send + 'static,
+{
+    let (_, schema_key) = open(schema, key)?;
+    let value_type = schema_key.value_type();
+    if T::static_variant_type().as_ref() != &*value_type {
+        return Err(Error::InvalidValue(format!(
+            "Key \"{}\" holds {}, not {}",
+            key,
+            value_type.as_str(),
+            T::static_variant_type().as_str()
+        )));
+    }
+    spawn_watch(schema, Some(key), |settings, key, sender| {
+        if let Some(value) = settings.value(key).get::<T>() {
+            return sender.unbounded_send(value).is_ok();
+        }
+        true
+    })
+}
+
+/// ## Watch every key of a schema
+/// Yields `(key, new value)` pairs.
+pub fn watch_schema(schema: &str) -> Result<Watch<(String, Variant)>> {
+    spawn_watch(schema, None, |settings, key, sender| {
+        sender
+            .unbounded_send((key.to_string(), settings.value(key)))
+            .is_ok()
+    })
+}
+
+/// Run the `changed` signal handler on a thread owning its own main context.
+/// `on_change` returns `false` once the stream is gone.
+fn spawn_watch<T, F>(schema: &str, key: Option<&str>, on_change: F) -> Result<Watch<T>>
+where
+    T: Send + 'static,
+    F: Fn(&Settings, &str, &UnboundedSender<T>) -> bool + Send + 'static,
+{
+    let schema = schema.to_string();
+    let key = key.map(|key| key.to_string());
+    let (sender, receiver) = mpsc::unbounded();
+    let (ready_sender, ready_receiver) = std::sync::mpsc::channel::<Result<MainLoop>>();
+
+    std::thread::Builder::new()
+        .name(format!("dconf-watch {}", schema))
+        .spawn(move || {
+            let context = MainContext::new();
+            context.push_thread_default();
+            let main_loop = MainLoop::new(Some(&context), false);
+            let (settings_schema, settings) =
+                match lookup(&schema).and_then(|(settings_schema, path)| {
+                    let settings = settings(&settings_schema, &schema, path)?;
+                    Ok((settings_schema, settings))
+                }) {
+                    Ok(opened) => opened,
+                    Err(error) => {
+                        let _ = ready_sender.send(Err(error));
+                        context.pop_thread_default();
+                        return;
+                    }
+                };
+            let handler_loop = main_loop.clone();
+            settings.connect_changed(key.as_deref(), move |settings, key| {
+                let keep_going = {
+                    let _access = access();
+                    on_change(settings, key, &sender)
+                };
+                if !keep_going {
+                    handler_loop.quit();
+                }
+            });
+            // GSettings only reports changes of keys that were read at least once
+            let keys = match &key {
+                Some(key) => vec![key.to_string()],
+                None => settings_schema
+                    .list_keys()
+                    .iter()
+                    .map(|key| key.to_string())
+                    .collect(),
+            };
+            {
+                let _access = access();
+                for key in keys {
+                    settings.value(&key);
+                }
+            }
+            let _ = ready_sender.send(Ok(main_loop.clone()));
+            main_loop.run();
+            context.pop_thread_default();
+        })?;
+
+    let main_loop = ready_receiver
+        .recv()
+        .map_err(|_| Error::Process("GSettings watch thread stopped".to_string()))??;
+    Ok(Watch {
+        receiver,
+        main_loop,
+    })
 }
@@ -0,0 +1,121 @@
+use std::fmt;
+
+/// ## Crate wide error
+/// Every public function of this crate returns this error instead of panicking,
+/// so callers can react to a missing service or a denied request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The bus, the service or the object is not there (gnome-shell restarting,
+    /// power-profiles-daemon not installed, no session bus...).
+    ServiceUnavailable(String),
+    /// The service refused the request (polkit, allowlisted callers...).
+    AccessDenied(String),
+    /// The value passed by the caller is not accepted.
+    InvalidValue(String),
+    /// The value returned by the service could not be understood.
+    Parse(String),
+    /// A helper process or a local library call failed.
+    Process(String),
+    /// The machine has no hardware for the request (no backlight, no battery...).
+    Unsupported(String),
+    /// The user dismissed the request (Escape on a picker or a selection).
+    Cancelled(String),
+    /// Any other D-Bus error, with its error name.
+    DBus { name: String, message: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::ServiceUnavailable(message) => write!(f, "Service unavailable: {}", message),
+            Error::AccessDenied(message) => write!(f, "Access denied: {}", message),
+            Error::InvalidValue(message) => write!(f, "Invalid value: {}", message),
+            Error::Parse(message) => write!(f, "Unable to parse: {}", message),
+            Error::Process(message) => write!(f, "Process failed: {}", message),
+            Error::Unsupported(message) => write!(f, "Unsupported: {}", message),
+            Error::Cancelled(message) => write!(f, "Cancelled: {}", message),
+            Error::DBus { name, message } => write!(f, "{}: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// ## `true` when the user dismissed the request (Escape on a picker or a selection)
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Error::Cancelled(_))
+    }
+    /// Map a D-Bus error name to the matching variant
+    fn from_dbus_name(name: &str, message: String) -> Error {
+        match name {
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+            | "org.freedesktop.DBus.Error.NameHasNoOwner"
+            | "org.freedesktop.DBus.Error.NoServer"
+            | "org.freedesktop.DBus.Error.UnknownObject"
+            | "org.freedesktop.DBus.Error.UnknownInterface"
+            | "org.freedesktop.DBus.Error.Disconnected" => Error::ServiceUnavailable(message),
+            "org.freedesktop.DBus.Error.AccessDenied"
+            | "org.freedesktop.DBus.Error.AuthFailed"
+            | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+            | "org.freedesktop.PolicyKit1.Error.NotAuthorized" => Error::AccessDenied(message),
+            "org.freedesktop.DBus.Error.InvalidArgs" => Error::InvalidValue(message),
+            // GNOME Shell reports it as an unmapped `G_IO_ERROR_CANCELLED`
+            "org.gtk.GDBus.UnmappedGError.Quark._g_2dio_2derror_2dquark.Code19"
+            | "org.freedesktop.portal.Error.Cancelled" => Error::Cancelled(message),
+            _ => Error::DBus {
+                name: name.to_string(),
+                message,
+            },
+        }
+    }
+}
+
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        match error {
+            zbus::Error::MethodError(name, message, _) => {
+                Error::from_dbus_name(name.as_str(), message.unwrap_or_default())
+            }
+            zbus::Error::FDO(error) => Error::from(*error),
+            zbus::Error::Address(message) | zbus::Error::Handshake(message) => {
+                Error::ServiceUnavailable(message)
+            }
+            zbus::Error::InputOutput(error) => Error::ServiceUnavailable(error.to_string()),
+            zbus::Error::Variant(error) => Error::Parse(error.to_string()),
+            error => Error::DBus {
+                name: "org.freedesktop.zbus.Error".to_string(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for Error {
+    fn from(error: zbus::fdo::Error) -> Self {
+        match error {
+            zbus::fdo::Error::ZBus(error) => Error::from(error),
+            error => {
+                use zbus::DBusError;
+                Error::from_dbus_name(
+                    error.name().as_str(),
+                    error.description().unwrap_or_default().to_string(),
+                )
+            }
+        }
+    }
+}
+
+impl From<zvariant::Error> for Error {
+    fn from(error: zvariant::Error) -> Self {
+        Error::Parse(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        Error::Process(error.to_string())
+    }
+}
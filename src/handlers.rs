@@ -1,4 +1,5 @@
 pub mod easy_gnome {
+    use serde::Serialize;
     use std::collections::HashMap;
     use zbus::{dbus_proxy, Result};
 
@@ -7,10 +8,31 @@ pub mod easy_gnome {
         default_service = "org.freedesktop.login1",
         default_path = "/org/freedesktop/login1"
     )]
-    trait PowerManagement {
+    pub(crate) trait PowerManagement {
         async fn Suspend(&self, arg: bool) -> Result<()>;
         async fn PowerOff(&self, arg: bool) -> Result<()>;
         async fn Reboot(&self, arg: bool) -> Result<()>;
+        async fn Hibernate(&self, arg: bool) -> Result<()>;
+        async fn HybridSleep(&self, arg: bool) -> Result<()>;
+        async fn SuspendThenHibernate(&self, arg: bool) -> Result<()>;
+        async fn CanPowerOff(&self) -> Result<String>;
+        async fn CanReboot(&self) -> Result<String>;
+        async fn CanSuspend(&self) -> Result<String>;
+        async fn CanHibernate(&self) -> Result<String>;
+        async fn CanHybridSleep(&self) -> Result<String>;
+        async fn CanSuspendThenHibernate(&self) -> Result<String>;
+        async fn Inhibit(
+            &self,
+            what: &str,
+            who: &str,
+            why: &str,
+            mode: &str,
+        ) -> Result<zvariant::OwnedFd>;
+        async fn ListInhibitors(&self) -> Result<Vec<(String, String, String, String, u32, u32)>>;
+        async fn ScheduleShutdown(&self, kind: &str, usec: u64) -> Result<()>;
+        async fn CancelScheduledShutdown(&self) -> Result<bool>;
+        #[dbus_proxy(property)]
+        fn ScheduledShutdown(&self) -> Result<(String, u64)>;
     }
 
     #[derive(PartialEq, Debug, Clone, Copy)]
@@ -21,32 +43,48 @@ pub mod easy_gnome {
     }
 
     impl PowerProfile {
-        fn as_str(&self) -> &'static str {
+        pub fn as_str(&self) -> &'static str {
             match self {
                 PowerProfile::PowerSaver => "power-saver",
                 PowerProfile::Balanced => "balanced",
                 PowerProfile::Performance => "performance",
             }
         }
-        fn from(profile: &str) -> PowerProfile {
+    }
+
+    impl std::str::FromStr for PowerProfile {
+        type Err = crate::Error;
+
+        fn from_str(profile: &str) -> crate::Result<PowerProfile> {
             match profile {
-                "power-saver" => PowerProfile::PowerSaver,
-                "balanced" => PowerProfile::Balanced,
-                "performance" => PowerProfile::Performance,
-                _ => PowerProfile::Balanced,
+                "power-saver" => Ok(PowerProfile::PowerSaver),
+                "balanced" => Ok(PowerProfile::Balanced),
+                "performance" => Ok(PowerProfile::Performance),
+                _ => Err(crate::Error::Parse(format!(
+                    "Unknown power profile {}",
+                    profile
+                ))),
             }
         }
     }
+    /// Destination, path and interface are set from [`power::PowerProfilesService`],
+    /// the daemon may only publish the legacy `net.hadess.PowerProfiles` names
     #[dbus_proxy(
-        interface = "net.hadess.PowerProfiles",
-        default_service = "net.hadess.PowerProfiles",
-        default_path = "/net/hadess/PowerProfiles"
+        interface = "org.freedesktop.UPower.PowerProfiles",
+        default_service = "org.freedesktop.UPower.PowerProfiles",
+        default_path = "/org/freedesktop/UPower/PowerProfiles"
     )]
-    trait PowerProfiles {
+    pub(crate) trait PowerProfiles {
         #[dbus_proxy(property)]
         fn ActiveProfile(&self) -> Result<String>;
         #[dbus_proxy(property)]
         fn set_ActiveProfile(&self, profile: String) -> Result<()>;
+        #[dbus_proxy(property)]
+        fn Profiles(&self) -> Result<Vec<HashMap<String, zvariant::OwnedValue>>>;
+        #[dbus_proxy(property)]
+        fn PerformanceDegraded(&self) -> Result<String>;
+        fn HoldProfile(&self, profile: &str, reason: &str, application_id: &str) -> Result<u32>;
+        fn ReleaseProfile(&self, cookie: u32) -> Result<()>;
     }
 
     // Shell extensions
@@ -55,7 +93,7 @@ pub mod easy_gnome {
         default_service = "org.gnome.Shell.Extensions",
         default_path = "/org/gnome/Shell/Extensions"
     )]
-    trait Extensions {
+    pub(crate) trait Extensions {
         async fn ListExtensions(
             &self,
         ) -> Result<HashMap<String, HashMap<String, zvariant::OwnedValue>>>;
@@ -63,6 +101,15 @@ pub mod easy_gnome {
         async fn EnableExtension(&self, uuid: String) -> Result<bool>;
         async fn DisableExtension(&self, uuid: String) -> Result<bool>;
         async fn UninstallExtension(&self, uuid: String) -> Result<bool>;
+        #[dbus_proxy(signal)]
+        fn ExtensionStateChanged(
+            &self,
+            uuid: String,
+            state: HashMap<String, zvariant::OwnedValue>,
+        ) -> Result<()>;
+        /// Older signal, emitted along with `ExtensionStateChanged`
+        #[dbus_proxy(signal)]
+        fn ExtensionStatusChanged(&self, uuid: String, state: i32, error: String) -> Result<()>;
     }
 
     /// # Extension states
@@ -76,7 +123,10 @@ pub mod easy_gnome {
     /// <member>99: UNINSTALLED</member>
     /// ```
     /// https://gitlab.gnome.org/GNOME/gnome-shell/-/blob/92d3c6e051958b31151bf9538205a71cab6f70d7/data/dbus-interfaces/org.gnome.Shell.Extensions.xml#L73
-    #[derive(Debug)]
+    /// Newer states, e.g. ACTIVATING and DEACTIVATING on GNOME 45, keep their number in `UNKNOWN`.
+    #[allow(non_camel_case_types)]
+    #[repr(u32)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
     pub enum ListExtensionState {
         ENABLED = 1,
         DISABLED = 2,
@@ -85,238 +135,1938 @@ pub mod easy_gnome {
         DOWNLOADING = 5,
         INITIALIZED = 6,
         UNINSTALLED = 99,
+        UNKNOWN(u32),
+    }
+    impl ListExtensionState {
+        /// ## Number of the state as sent by GNOME Shell
+        pub fn number(&self) -> u32 {
+            match self {
+                ListExtensionState::ENABLED => 1,
+                ListExtensionState::DISABLED => 2,
+                ListExtensionState::ERROR => 3,
+                ListExtensionState::OUT_OF_DATE => 4,
+                ListExtensionState::DOWNLOADING => 5,
+                ListExtensionState::INITIALIZED => 6,
+                ListExtensionState::UNINSTALLED => 99,
+                ListExtensionState::UNKNOWN(state) => *state,
+            }
+        }
     }
-    #[derive(Debug)]
+    impl From<f64> for ListExtensionState {
+        fn from(state: f64) -> ListExtensionState {
+            match state as u32 {
+                1 => ListExtensionState::ENABLED,
+                2 => ListExtensionState::DISABLED,
+                3 => ListExtensionState::ERROR,
+                4 => ListExtensionState::OUT_OF_DATE,
+                5 => ListExtensionState::DOWNLOADING,
+                6 => ListExtensionState::INITIALIZED,
+                99 => ListExtensionState::UNINSTALLED,
+                state => ListExtensionState::UNKNOWN(state),
+            }
+        }
+    }
+    /// ## Where an extension is installed, the `type` field
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum ExtensionType {
+        /// Installed by the distribution, in `/usr/share/gnome-shell/extensions`
+        System,
+        /// Installed by the user, in `~/.local/share/gnome-shell/extensions`
+        PerUser,
+        Other(u32),
+    }
+    impl From<f64> for ExtensionType {
+        fn from(kind: f64) -> ExtensionType {
+            match kind as u32 {
+                1 => ExtensionType::System,
+                2 => ExtensionType::PerUser,
+                kind => ExtensionType::Other(kind),
+            }
+        }
+    }
+    /// ## Extension as listed by GNOME Shell
+    /// Fields missing from the shell reply, e.g. `url` for system extensions
+    /// or `enabled` before GNOME 45, are `None`.
+    #[derive(Debug, Clone, PartialEq, Serialize)]
     pub struct ListExtension {
         pub uuid: String,
+        /// The uuid when the extension has no name
         pub name: String,
         pub description: String,
-        pub state: ListExtensionState,
+        /// `None` when the shell sent no state
+        pub state: Option<ListExtensionState>,
         pub version: String,
-        pub url: String,
+        pub url: Option<String>,
+        #[serde(rename = "type")]
+        pub kind: Option<ExtensionType>,
+        /// Directory of the extension
+        pub path: Option<String>,
+        /// Why the extension failed to load, with the `ERROR` state
+        pub error: Option<String>,
+        pub has_prefs: Option<bool>,
+        pub has_update: Option<bool>,
+        /// `false` when the extension is locked down by the administrator
+        pub can_change: Option<bool>,
+        /// GNOME Shell versions declared by the extension
+        pub shell_version: Option<Vec<String>>,
+        pub settings_schema: Option<String>,
+        pub enabled: Option<bool>,
+    }
+    impl ListExtension {
+        /// ## Read the info dictionary of `ListExtensions`, `GetExtensionInfo` or `ExtensionStateChanged`
+        /// Never fails: malformed fields, and empty strings, are treated as missing.
+        pub fn from_info(
+            uuid: &str,
+            info: &HashMap<String, zvariant::OwnedValue>,
+        ) -> ListExtension {
+            fn field<T: TryFrom<zvariant::Value<'static>>>(
+                info: &HashMap<String, zvariant::OwnedValue>,
+                name: &str,
+            ) -> Option<T> {
+                info.get(name)
+                    .and_then(|value| T::try_from((**value).clone()).ok())
+            }
+            let text = |name: &str| field::<String>(info, name).filter(|text| !text.is_empty());
+            // The version comes from metadata.json, a number or a string
+            let version = text("version")
+                .or_else(|| field::<f64>(info, "version").map(|version| version.to_string()))
+                .unwrap_or_default();
+            ListExtension {
+                uuid: uuid.to_string(),
+                name: text("name").unwrap_or_else(|| uuid.to_string()),
+                description: text("description").unwrap_or_default(),
+                state: field::<f64>(info, "state").map(ListExtensionState::from),
+                version,
+                url: text("url"),
+                kind: field::<f64>(info, "type").map(ExtensionType::from),
+                path: text("path"),
+                error: text("error"),
+                has_prefs: field(info, "hasPrefs"),
+                has_update: field(info, "hasUpdate"),
+                can_change: field(info, "canChange"),
+                shell_version: field(info, "shell-version"),
+                settings_schema: text("settings-schema"),
+                enabled: field(info, "enabled"),
+            }
+        }
     }
     impl ExtensionsProxy<'static> {
-        async fn launch_extension_prefs(&self, uuid: &str) -> Result<()> {
-            let _reply = self
-                .LaunchExtensionPrefs(uuid.to_string())
-                .await
-                .unwrap_or_else(|_| ());
+        pub(crate) async fn launch_extension_prefs(&self, uuid: &str) -> crate::Result<()> {
+            self.LaunchExtensionPrefs(uuid.to_string()).await?;
             Ok(())
         }
-        async fn list_extensions(&self) -> Vec<ListExtension> {
-            let list = self.ListExtensions().await.unwrap();
-            let mut list_extension: Vec<ListExtension> = Vec::new();
-            for extension in list {
-                let uuid = extension.0;
-                let name = extension
-                    .1
-                    .get("name")
-                    .unwrap()
-                    .to_owned()
-                    .try_into()
-                    .unwrap();
-                let description = extension
-                    .1
-                    .get("description")
-                    .unwrap()
-                    .to_owned()
-                    .try_into()
-                    .unwrap();
-                let try_version = extension.1.get("version");
-                let version = match try_version {
-                    Some(version) => version
-                        .to_owned()
-                        .try_into()
-                        .unwrap_or_else(|_| "".to_string()),
-                    None => "".to_string(),
-                };
-                let state_number: f64 = extension
-                    .1
-                    .get("state")
-                    .unwrap()
-                    .to_owned()
-                    .try_into()
-                    .unwrap();
-                let state: ListExtensionState = match state_number {
-                    1.0 => ListExtensionState::ENABLED,
-                    2.0 => ListExtensionState::DISABLED,
-                    3.0 => ListExtensionState::ERROR,
-                    4.0 => ListExtensionState::OUT_OF_DATE,
-                    5.0 => ListExtensionState::DOWNLOADING,
-                    6.0 => ListExtensionState::INITIALIZED,
-                    99.0 => ListExtensionState::UNINSTALLED,
-                    _ => ListExtensionState::UNINSTALLED,
-                };
-                let url = extension
-                    .1
-                    .get("url")
-                    .unwrap()
-                    .to_owned()
-                    .try_into()
-                    .unwrap();
-                let item = ListExtension {
-                    uuid,
-                    name,
-                    description,
-                    version,
-                    state,
-                    url,
-                };
-                list_extension.push(item);
-            }
-            list_extension
+        pub(crate) async fn list_extensions(&self) -> crate::Result<Vec<ListExtension>> {
+            let list = self.ListExtensions().await?;
+            Ok(list
+                .iter()
+                .map(|(uuid, info)| ListExtension::from_info(uuid, info))
+                .collect())
         }
     }
     // Shell screenshot
     #[dbus_proxy(
         interface = "org.gnome.Shell.Screenshot",
+        default_service = "org.gnome.Shell.Screenshot",
         default_path = "/org/gnome/Shell/Screenshot"
     )]
-    trait Screenshot {
+    pub(crate) trait Screenshot {
         async fn PickColor(&self) -> Result<HashMap<String, zvariant::OwnedValue>>;
+        async fn Screenshot(
+            &self,
+            include_cursor: bool,
+            flash: bool,
+            filename: String,
+        ) -> Result<(bool, String)>;
+        async fn ScreenshotWindow(
+            &self,
+            include_frame: bool,
+            include_cursor: bool,
+            flash: bool,
+            filename: String,
+        ) -> Result<(bool, String)>;
+        async fn ScreenshotArea(
+            &self,
+            x: i32,
+            y: i32,
+            width: i32,
+            height: i32,
+            flash: bool,
+            filename: String,
+        ) -> Result<(bool, String)>;
+        async fn SelectArea(&self) -> Result<(i32, i32, i32, i32)>;
+        async fn FlashArea(&self, x: i32, y: i32, width: i32, height: i32) -> Result<()>;
     }
     impl ScreenshotProxy<'static> {
-        async fn pick_color(&self) -> (f64, f64, f64) {
-            let pick_color = self.PickColor().await.unwrap();
-            let value = pick_color.get("color").unwrap();
-            let (r, g, b): (f64, f64, f64) = value.to_owned().try_into().unwrap();
+        pub(crate) async fn pick_color(&self) -> crate::Result<Option<(f64, f64, f64)>> {
+            let pick_color = match self.PickColor().await {
+                Ok(pick_color) => pick_color,
+                Err(error) => {
+                    let error = crate::Error::from(error);
+                    if error.is_cancelled() {
+                        return Ok(None);
+                    }
+                    return Err(error);
+                }
+            };
+            let value = pick_color
+                .get("color")
+                .ok_or_else(|| crate::Error::Parse("PickColor reply has no color".to_string()))?;
+            let (r, g, b): (f64, f64, f64) = value.to_owned().try_into()?;
+
+            Ok(Some((r, g, b)))
+        }
+    }
+
+    #[dbus_proxy(
+        interface = "org.gnome.Shell.Screencast",
+        default_service = "org.gnome.Shell.Screencast",
+        default_path = "/org/gnome/Shell/Screencast"
+    )]
+    pub(crate) trait Screencast {
+        async fn Screencast(
+            &self,
+            file_template: &str,
+            options: HashMap<&str, zvariant::Value<'_>>,
+        ) -> Result<(bool, String)>;
+        async fn ScreencastArea(
+            &self,
+            x: i32,
+            y: i32,
+            width: i32,
+            height: i32,
+            file_template: &str,
+            options: HashMap<&str, zvariant::Value<'_>>,
+        ) -> Result<(bool, String)>;
+        async fn StopScreencast(&self) -> Result<bool>;
+    }
+
+    #[dbus_proxy(
+        interface = "org.gnome.SettingsDaemon.Power.Screen",
+        default_service = "org.gnome.SettingsDaemon.Power",
+        default_path = "/org/gnome/SettingsDaemon/Power"
+    )]
+    pub(crate) trait Screen {
+        #[dbus_proxy(property)]
+        fn Brightness(&self) -> Result<i32>;
+        #[dbus_proxy(property)]
+        fn set_Brightness(&self, brightness: i32) -> Result<()>;
+        fn StepUp(&self) -> Result<()>;
+        fn StepDown(&self) -> Result<()>;
+    }
+
+    pub mod power {
+        use std::collections::HashMap;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        use futures_util::StreamExt;
+        use zbus::Connection;
+
+        use crate::client::name_has_owner;
+        use crate::{Error, EventStream, GnomeClient, Result};
+
+        use super::{PowerProfile, PowerProfilesProxy};
+
+        const SERVICE: &str = "org.freedesktop.UPower.PowerProfiles";
+        const LEGACY_SERVICE: &str = "net.hadess.PowerProfiles";
+        const TUNED_SERVICE: &str = "com.redhat.tuned";
+
+        /// ## Program publishing the power profiles
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum PowerProfilesProvider {
+            PowerProfilesDaemon,
+            /// tuned-ppd, the power-profiles-daemon compatibility layer of tuned
+            TunedPpd,
+        }
+
+        /// ## Power profiles service found on the system bus
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct PowerProfilesService {
+            pub bus_name: &'static str,
+            pub path: &'static str,
+            pub interface: &'static str,
+            pub provider: PowerProfilesProvider,
+        }
+
+        impl PowerProfilesService {
+            /// ## Whether only the `net.hadess.PowerProfiles` alias is published
+            pub fn is_legacy(&self) -> bool {
+                self.bus_name == LEGACY_SERVICE
+            }
+            pub(crate) fn proxy_builder<'a>(
+                &self,
+                connection: &Connection,
+            ) -> Result<zbus::ProxyBuilder<'a, PowerProfilesProxy<'a>>> {
+                Ok(PowerProfilesProxy::builder(connection)
+                    .destination(self.bus_name)?
+                    .path(self.path)?
+                    .interface(self.interface)?)
+            }
+            async fn detect(connection: &Connection) -> Result<PowerProfilesService> {
+                let (bus_name, path) = if name_has_owner(connection, SERVICE).await? {
+                    (SERVICE, "/org/freedesktop/UPower/PowerProfiles")
+                } else if name_has_owner(connection, LEGACY_SERVICE).await? {
+                    (LEGACY_SERVICE, "/net/hadess/PowerProfiles")
+                } else {
+                    return Err(Error::ServiceUnavailable(format!(
+                        "No power profiles service, neither {} nor {} is on the system bus \
+                         (install power-profiles-daemon or tuned-ppd)",
+                        SERVICE, LEGACY_SERVICE
+                    )));
+                };
+                let provider = if name_has_owner(connection, TUNED_SERVICE).await? {
+                    PowerProfilesProvider::TunedPpd
+                } else {
+                    PowerProfilesProvider::PowerProfilesDaemon
+                };
+                Ok(PowerProfilesService {
+                    bus_name,
+                    path,
+                    interface: bus_name,
+                    provider,
+                })
+            }
+        }
 
-            (r, g, b)
+        /// ## Profile supported by the machine
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct ProfileInfo {
+            pub profile: PowerProfile,
+            /// `placeholder` when power-profiles-daemon only emulates the profile
+            pub driver: String,
+            /// Driver changing the CPU settings, e.g. `intel_pstate` or `amd_pstate`
+            pub cpu_driver: Option<String>,
+            /// Driver changing the firmware settings, e.g. `platform_profile`
+            pub platform_driver: Option<String>,
+        }
+
+        impl ProfileInfo {
+            fn from_dict(profile: &HashMap<String, zvariant::OwnedValue>) -> Result<ProfileInfo> {
+                let text = |key: &str| -> Option<String> {
+                    profile
+                        .get(key)
+                        .and_then(|value| String::try_from(value.clone()).ok())
+                };
+                let name = text("Profile")
+                    .ok_or_else(|| Error::Parse("Power profile without a name".to_string()))?;
+                Ok(ProfileInfo {
+                    profile: name.parse()?,
+                    driver: text("Driver").unwrap_or_default(),
+                    cpu_driver: text("CpuDriver"),
+                    platform_driver: text("PlatformDriver"),
+                })
+            }
+        }
+
+        /// ## Reason for the performance profile to run slower
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub enum DegradedReason {
+            /// The computer sits on someone's lap
+            LapDetected,
+            /// The computer is too hot
+            HighOperatingTemperature,
+            /// Reason unknown to this crate
+            Other(String),
+        }
+
+        impl DegradedReason {
+            fn from_property(reason: &str) -> Option<DegradedReason> {
+                match reason {
+                    "" => None,
+                    "lap-detected" => Some(DegradedReason::LapDetected),
+                    "high-operating-temperature" => Some(DegradedReason::HighOperatingTemperature),
+                    reason => Some(DegradedReason::Other(reason.to_string())),
+                }
+            }
+        }
+
+        /// ## Profile held through [`GnomeClient::hold_power_profile`]
+        /// Dropping the guard releases the hold from a short-lived thread,
+        /// use [`ProfileHold::release`] to wait for it and see errors.
+        #[derive(Debug)]
+        pub struct ProfileHold {
+            proxy: Option<PowerProfilesProxy<'static>>,
+            profile: PowerProfile,
+            cookie: u32,
+        }
+
+        impl ProfileHold {
+            pub fn profile(&self) -> PowerProfile {
+                self.profile
+            }
+            pub fn cookie(&self) -> u32 {
+                self.cookie
+            }
+            pub async fn release(mut self) -> Result<()> {
+                if let Some(proxy) = self.proxy.take() {
+                    proxy.ReleaseProfile(self.cookie).await?;
+                }
+                Ok(())
+            }
+        }
+
+        impl Drop for ProfileHold {
+            fn drop(&mut self) {
+                if let Some(proxy) = self.proxy.take() {
+                    let cookie = self.cookie;
+                    std::thread::spawn(move || {
+                        let _ = zbus::block_on(proxy.ReleaseProfile(cookie));
+                    });
+                }
+            }
+        }
+
+        /// ## Power action handled by login1
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum PowerAction {
+            PowerOff,
+            Reboot,
+            Suspend,
+            Hibernate,
+            HybridSleep,
+            SuspendThenHibernate,
+        }
+
+        impl PowerAction {
+            pub const ALL: [PowerAction; 6] = [
+                PowerAction::PowerOff,
+                PowerAction::Reboot,
+                PowerAction::Suspend,
+                PowerAction::Hibernate,
+                PowerAction::HybridSleep,
+                PowerAction::SuspendThenHibernate,
+            ];
+        }
+
+        /// ## Answer of the login1 `Can*` queries
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Capability {
+            /// Allowed without authentication
+            Yes,
+            /// Not allowed
+            No,
+            /// Allowed after polkit authentication, only when interaction is allowed
+            Challenge,
+            /// Not supported by the hardware or the configuration (`na`)
+            NotApplicable,
+        }
+
+        impl Capability {
+            /// ## Whether the action can be performed, possibly after authentication
+            pub fn is_available(&self) -> bool {
+                matches!(self, Capability::Yes | Capability::Challenge)
+            }
+        }
+
+        impl std::str::FromStr for Capability {
+            type Err = Error;
+
+            fn from_str(capability: &str) -> Result<Capability> {
+                match capability {
+                    "yes" => Ok(Capability::Yes),
+                    "no" => Ok(Capability::No),
+                    "challenge" => Ok(Capability::Challenge),
+                    "na" => Ok(Capability::NotApplicable),
+                    _ => Err(Error::Parse(format!("Unknown capability {}", capability))),
+                }
+            }
+        }
+
+        /// ## Operation blocked or delayed by an inhibitor
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum InhibitWhat {
+            Shutdown,
+            Sleep,
+            Idle,
+            HandlePowerKey,
+            HandleSuspendKey,
+            HandleHibernateKey,
+            HandleLidSwitch,
+        }
+
+        impl InhibitWhat {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    InhibitWhat::Shutdown => "shutdown",
+                    InhibitWhat::Sleep => "sleep",
+                    InhibitWhat::Idle => "idle",
+                    InhibitWhat::HandlePowerKey => "handle-power-key",
+                    InhibitWhat::HandleSuspendKey => "handle-suspend-key",
+                    InhibitWhat::HandleHibernateKey => "handle-hibernate-key",
+                    InhibitWhat::HandleLidSwitch => "handle-lid-switch",
+                }
+            }
+        }
+
+        impl std::str::FromStr for InhibitWhat {
+            type Err = Error;
+
+            fn from_str(what: &str) -> Result<InhibitWhat> {
+                match what {
+                    "shutdown" => Ok(InhibitWhat::Shutdown),
+                    "sleep" => Ok(InhibitWhat::Sleep),
+                    "idle" => Ok(InhibitWhat::Idle),
+                    "handle-power-key" => Ok(InhibitWhat::HandlePowerKey),
+                    "handle-suspend-key" => Ok(InhibitWhat::HandleSuspendKey),
+                    "handle-hibernate-key" => Ok(InhibitWhat::HandleHibernateKey),
+                    "handle-lid-switch" => Ok(InhibitWhat::HandleLidSwitch),
+                    _ => Err(Error::Parse(format!("Unknown inhibitor type {}", what))),
+                }
+            }
+        }
+
+        /// ## How an inhibitor holds the operation back
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum InhibitMode {
+            /// The operation is refused while the inhibitor is held
+            Block,
+            /// Like `Block`, but only for operations requested without forcing them
+            BlockWeak,
+            /// The operation waits until the inhibitor is released, or for `InhibitDelayMaxSec`
+            Delay,
+        }
+
+        impl InhibitMode {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    InhibitMode::Block => "block",
+                    InhibitMode::BlockWeak => "block-weak",
+                    InhibitMode::Delay => "delay",
+                }
+            }
+        }
+
+        impl std::str::FromStr for InhibitMode {
+            type Err = Error;
+
+            fn from_str(mode: &str) -> Result<InhibitMode> {
+                match mode {
+                    "block" => Ok(InhibitMode::Block),
+                    "block-weak" => Ok(InhibitMode::BlockWeak),
+                    "delay" => Ok(InhibitMode::Delay),
+                    _ => Err(Error::Parse(format!("Unknown inhibitor mode {}", mode))),
+                }
+            }
+        }
+
+        /// ## Inhibitor taken by this process
+        /// login1 releases the inhibitor when the file descriptor is closed,
+        /// that is when the guard is dropped.
+        #[derive(Debug)]
+        pub struct InhibitGuard {
+            what: Vec<InhibitWhat>,
+            mode: InhibitMode,
+            _fd: zvariant::OwnedFd,
+        }
+
+        impl InhibitGuard {
+            pub fn what(&self) -> &[InhibitWhat] {
+                &self.what
+            }
+            pub fn mode(&self) -> InhibitMode {
+                self.mode
+            }
+            /// ## Release the inhibitor now
+            pub fn release(self) {}
+        }
+
+        /// ## Inhibitor held by any process
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct Inhibitor {
+            pub what: Vec<InhibitWhat>,
+            /// Application holding the inhibitor
+            pub who: String,
+            /// Reason shown to the user
+            pub why: String,
+            pub mode: InhibitMode,
+            pub uid: u32,
+            pub pid: u32,
+        }
+
+        /// ## Action run by a scheduled shutdown
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum ShutdownKind {
+            PowerOff,
+            Reboot,
+            Halt,
+            Kexec,
+        }
+
+        impl ShutdownKind {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    ShutdownKind::PowerOff => "poweroff",
+                    ShutdownKind::Reboot => "reboot",
+                    ShutdownKind::Halt => "halt",
+                    ShutdownKind::Kexec => "kexec",
+                }
+            }
+        }
+
+        impl std::str::FromStr for ShutdownKind {
+            type Err = Error;
+
+            /// The `dry-` variants scheduled by other tools map to the real action
+            fn from_str(kind: &str) -> Result<ShutdownKind> {
+                match kind.trim_start_matches("dry-") {
+                    "poweroff" => Ok(ShutdownKind::PowerOff),
+                    "reboot" => Ok(ShutdownKind::Reboot),
+                    "halt" => Ok(ShutdownKind::Halt),
+                    "kexec" => Ok(ShutdownKind::Kexec),
+                    _ => Err(Error::Parse(format!("Unknown shutdown type {}", kind))),
+                }
+            }
+        }
+
+        /// ## Shutdown waiting in login1
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct ScheduledShutdown {
+            pub kind: ShutdownKind,
+            pub at: SystemTime,
+        }
+
+        impl ScheduledShutdown {
+            /// ## Time left before the shutdown, zero once it is due
+            pub fn remaining(&self) -> Duration {
+                self.at
+                    .duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO)
+            }
+        }
+
+        /// ## Shutdown scheduled through [`GnomeClient::schedule_shutdown`]
+        #[derive(Clone, Copy)]
+        pub struct ShutdownHandle<'a> {
+            client: &'a GnomeClient,
+            scheduled: ScheduledShutdown,
+        }
+
+        impl ShutdownHandle<'_> {
+            pub fn scheduled(&self) -> ScheduledShutdown {
+                self.scheduled
+            }
+            pub fn remaining(&self) -> Duration {
+                self.scheduled.remaining()
+            }
+            /// ## Whether this shutdown is still the one waiting in login1
+            /// `false` once cancelled or replaced by another schedule.
+            pub async fn is_pending(&self) -> Result<bool> {
+                Ok(self.client.scheduled_shutdown().await? == Some(self.scheduled))
+            }
+            /// ## Cancel this shutdown
+            /// Returns `false` when it is no longer pending, another schedule is left untouched.
+            pub async fn cancel(&self) -> Result<bool> {
+                if !self.is_pending().await? {
+                    return Ok(false);
+                }
+                self.client.cancel_scheduled_shutdown().await
+            }
+        }
+
+        impl GnomeClient {
+            /// ## Schedule a shutdown or a reboot at a given time
+            /// Replaces any shutdown already scheduled. Logged in users are warned by login1.
+            pub async fn schedule_shutdown(
+                &self,
+                kind: ShutdownKind,
+                at: SystemTime,
+            ) -> Result<ShutdownHandle<'_>> {
+                let usec = at
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|_| Error::InvalidValue("Shutdown time before 1970".to_string()))?
+                    .as_micros();
+                let usec = u64::try_from(usec)
+                    .map_err(|_| Error::InvalidValue("Shutdown time too far away".to_string()))?;
+                self.power_management_proxy()
+                    .await?
+                    .ScheduleShutdown(kind.as_str(), usec)
+                    .await?;
+                Ok(ShutdownHandle {
+                    client: self,
+                    scheduled: ScheduledShutdown {
+                        kind,
+                        at: UNIX_EPOCH + Duration::from_micros(usec),
+                    },
+                })
+            }
+            /// ## Schedule a shutdown or a reboot after a delay
+            pub async fn schedule_shutdown_in(
+                &self,
+                kind: ShutdownKind,
+                delay: Duration,
+            ) -> Result<ShutdownHandle<'_>> {
+                self.schedule_shutdown(kind, SystemTime::now() + delay)
+                    .await
+            }
+            /// ## Shutdown currently scheduled, if any
+            pub async fn scheduled_shutdown(&self) -> Result<Option<ScheduledShutdown>> {
+                let (kind, usec) = self
+                    .power_management_proxy()
+                    .await?
+                    .ScheduledShutdown()
+                    .await?;
+                if kind.is_empty() || usec == 0 {
+                    return Ok(None);
+                }
+                Ok(Some(ScheduledShutdown {
+                    kind: kind.parse()?,
+                    at: UNIX_EPOCH + Duration::from_micros(usec),
+                }))
+            }
+            /// ## Cancel the scheduled shutdown, whoever scheduled it
+            /// Returns `false` when nothing was scheduled.
+            pub async fn cancel_scheduled_shutdown(&self) -> Result<bool> {
+                Ok(self
+                    .power_management_proxy()
+                    .await?
+                    .CancelScheduledShutdown()
+                    .await?)
+            }
+            /// ## Block or delay shutdown, sleep, idle or the power keys
+            /// The inhibitor lasts as long as the returned guard.
+            pub async fn inhibit(
+                &self,
+                what: &[InhibitWhat],
+                who: &str,
+                why: &str,
+                mode: InhibitMode,
+            ) -> Result<InhibitGuard> {
+                if what.is_empty() {
+                    return Err(Error::InvalidValue(
+                        "Nothing to inhibit, what is empty".to_string(),
+                    ));
+                }
+                let joined: Vec<&str> = what.iter().map(|what| what.as_str()).collect();
+                let fd = self
+                    .power_management_proxy()
+                    .await?
+                    .Inhibit(&joined.join(":"), who, why, mode.as_str())
+                    .await?;
+                Ok(InhibitGuard {
+                    what: what.to_vec(),
+                    mode,
+                    _fd: fd,
+                })
+            }
+            /// ## Inhibitors currently held on the system
+            /// Types this crate does not know are left out of `what`,
+            /// inhibitors with a mode it does not know are left out of the list.
+            pub async fn list_inhibitors(&self) -> Result<Vec<Inhibitor>> {
+                let inhibitors = self
+                    .power_management_proxy()
+                    .await?
+                    .ListInhibitors()
+                    .await?;
+                Ok(inhibitors
+                    .into_iter()
+                    .filter_map(|(what, who, why, mode, uid, pid)| {
+                        Some(Inhibitor {
+                            what: what
+                                .split(':')
+                                .filter_map(|what| what.parse().ok())
+                                .collect(),
+                            who,
+                            why,
+                            mode: mode.parse().ok()?,
+                            uid,
+                            pid,
+                        })
+                    })
+                    .collect())
+            }
+            /// ## Perform a power action
+            /// With `interactive` polkit may ask the user to authenticate,
+            /// without it the call fails with `Error::AccessDenied` when authentication is needed.
+            pub async fn perform(&self, action: PowerAction, interactive: bool) -> Result<()> {
+                let proxy = self.power_management_proxy().await?;
+                match action {
+                    PowerAction::PowerOff => proxy.PowerOff(interactive).await?,
+                    PowerAction::Reboot => proxy.Reboot(interactive).await?,
+                    PowerAction::Suspend => proxy.Suspend(interactive).await?,
+                    PowerAction::Hibernate => proxy.Hibernate(interactive).await?,
+                    PowerAction::HybridSleep => proxy.HybridSleep(interactive).await?,
+                    PowerAction::SuspendThenHibernate => {
+                        proxy.SuspendThenHibernate(interactive).await?
+                    }
+                }
+                Ok(())
+            }
+            /// ## Whether the system allows a power action
+            pub async fn can(&self, action: PowerAction) -> Result<Capability> {
+                let proxy = self.power_management_proxy().await?;
+                let capability = match action {
+                    PowerAction::PowerOff => proxy.CanPowerOff().await?,
+                    PowerAction::Reboot => proxy.CanReboot().await?,
+                    PowerAction::Suspend => proxy.CanSuspend().await?,
+                    PowerAction::Hibernate => proxy.CanHibernate().await?,
+                    PowerAction::HybridSleep => proxy.CanHybridSleep().await?,
+                    PowerAction::SuspendThenHibernate => proxy.CanSuspendThenHibernate().await?,
+                };
+                capability.parse()
+            }
+            /// ## Capability of every power action, e.g. to build a power menu
+            pub async fn capabilities(&self) -> Result<Vec<(PowerAction, Capability)>> {
+                let mut capabilities = Vec::new();
+                for action in PowerAction::ALL {
+                    capabilities.push((action, self.can(action).await?));
+                }
+                Ok(capabilities)
+            }
+            pub async fn power_off(&self) -> Result<()> {
+                self.perform(PowerAction::PowerOff, true).await
+            }
+            pub async fn suspend(&self) -> Result<()> {
+                self.perform(PowerAction::Suspend, true).await
+            }
+            pub async fn reboot(&self) -> Result<()> {
+                self.perform(PowerAction::Reboot, true).await
+            }
+            pub async fn hibernate(&self) -> Result<()> {
+                self.perform(PowerAction::Hibernate, true).await
+            }
+            pub async fn hybrid_sleep(&self) -> Result<()> {
+                self.perform(PowerAction::HybridSleep, true).await
+            }
+            pub async fn suspend_then_hibernate(&self) -> Result<()> {
+                self.perform(PowerAction::SuspendThenHibernate, true).await
+            }
+            /// ## Service answering the power profile calls
+            /// `org.freedesktop.UPower.PowerProfiles` is preferred over `net.hadess.PowerProfiles`.
+            /// Detected once per client.
+            pub async fn power_profiles_service(&self) -> Result<PowerProfilesService> {
+                let connection = self.system().await?;
+                Ok(*self
+                    .power_profiles_service
+                    .get_or_try_init(|| PowerProfilesService::detect(connection))
+                    .await?)
+            }
+            pub async fn power_profile(&self) -> Result<PowerProfile> {
+                let profile = self.power_profiles_proxy().await?.ActiveProfile().await?;
+                profile.parse()
+            }
+            /// ## Profiles supported by the machine, with their drivers
+            /// Profiles unknown to this crate are left out.
+            pub async fn power_profiles(&self) -> Result<Vec<ProfileInfo>> {
+                let profiles = self.power_profiles_proxy().await?.Profiles().await?;
+                Ok(profiles
+                    .iter()
+                    .filter_map(|profile| ProfileInfo::from_dict(profile).ok())
+                    .collect())
+            }
+            /// ## Why the performance profile runs slower, `None` when it does not
+            pub async fn performance_degraded(&self) -> Result<Option<DegradedReason>> {
+                let reason = self
+                    .power_profiles_proxy()
+                    .await?
+                    .PerformanceDegraded()
+                    .await?;
+                Ok(DegradedReason::from_property(&reason))
+            }
+            /// ## Switch to a profile until the hold is released
+            /// Only `Performance` and `PowerSaver` can be held. The hold ends when the guard
+            /// is dropped or when this process leaves the bus.
+            pub async fn hold_power_profile(
+                &self,
+                profile: PowerProfile,
+                reason: &str,
+                application_id: &str,
+            ) -> Result<ProfileHold> {
+                if profile == PowerProfile::Balanced {
+                    return Err(Error::InvalidValue(
+                        "Only performance and power-saver can be held".to_string(),
+                    ));
+                }
+                let proxy = self.power_profiles_proxy().await?;
+                let cookie = proxy
+                    .HoldProfile(profile.as_str(), reason, application_id)
+                    .await?;
+                Ok(ProfileHold {
+                    proxy: Some(proxy.clone()),
+                    profile,
+                    cookie,
+                })
+            }
+            /// ## Stream of active profile changes, from the panel, powerprofilesctl or this crate
+            pub async fn power_profile_changes(&self) -> Result<EventStream<PowerProfile>> {
+                let service = self.power_profiles_service().await?;
+                let proxy = service.proxy_builder(self.system().await?)?.build().await?;
+                Ok(proxy
+                    .receive_ActiveProfile_changed()
+                    .await
+                    .then(|change| async move { change.get().await?.parse() })
+                    .boxed())
+            }
+            pub async fn set_power_profile(&self, profile: PowerProfile) -> Result<()> {
+                self.power_profiles_proxy()
+                    .await?
+                    .set_ActiveProfile(profile.as_str().to_string())
+                    .await?;
+                Ok(())
+            }
+        }
+
+        pub async fn power_off() -> Result<()> {
+            GnomeClient::shared().power_off().await
+        }
+        pub async fn suspend() -> Result<()> {
+            GnomeClient::shared().suspend().await
+        }
+        pub async fn reboot() -> Result<()> {
+            GnomeClient::shared().reboot().await
+        }
+        pub async fn hibernate() -> Result<()> {
+            GnomeClient::shared().hibernate().await
+        }
+        pub async fn hybrid_sleep() -> Result<()> {
+            GnomeClient::shared().hybrid_sleep().await
+        }
+        pub async fn suspend_then_hibernate() -> Result<()> {
+            GnomeClient::shared().suspend_then_hibernate().await
+        }
+        pub async fn perform(action: PowerAction, interactive: bool) -> Result<()> {
+            GnomeClient::shared().perform(action, interactive).await
+        }
+        pub async fn can(action: PowerAction) -> Result<Capability> {
+            GnomeClient::shared().can(action).await
+        }
+        pub async fn capabilities() -> Result<Vec<(PowerAction, Capability)>> {
+            GnomeClient::shared().capabilities().await
+        }
+        pub async fn inhibit(
+            what: &[InhibitWhat],
+            who: &str,
+            why: &str,
+            mode: InhibitMode,
+        ) -> Result<InhibitGuard> {
+            GnomeClient::shared().inhibit(what, who, why, mode).await
+        }
+        pub async fn list_inhibitors() -> Result<Vec<Inhibitor>> {
+            GnomeClient::shared().list_inhibitors().await
+        }
+        pub async fn schedule_shutdown(
+            kind: ShutdownKind,
+            at: SystemTime,
+        ) -> Result<ShutdownHandle<'static>> {
+            GnomeClient::shared().schedule_shutdown(kind, at).await
+        }
+        pub async fn schedule_shutdown_in(
+            kind: ShutdownKind,
+            delay: Duration,
+        ) -> Result<ShutdownHandle<'static>> {
+            GnomeClient::shared()
+                .schedule_shutdown_in(kind, delay)
+                .await
+        }
+        pub async fn scheduled_shutdown() -> Result<Option<ScheduledShutdown>> {
+            GnomeClient::shared().scheduled_shutdown().await
+        }
+        pub async fn cancel_scheduled_shutdown() -> Result<bool> {
+            GnomeClient::shared().cancel_scheduled_shutdown().await
+        }
+        pub async fn get_power_profile() -> Result<PowerProfile> {
+            GnomeClient::shared().power_profile().await
+        }
+        pub async fn set_power_profile(profile: PowerProfile) -> Result<()> {
+            GnomeClient::shared().set_power_profile(profile).await
+        }
+        pub async fn power_profiles_service() -> Result<PowerProfilesService> {
+            GnomeClient::shared().power_profiles_service().await
+        }
+        pub async fn get_power_profiles() -> Result<Vec<ProfileInfo>> {
+            GnomeClient::shared().power_profiles().await
+        }
+        pub async fn performance_degraded() -> Result<Option<DegradedReason>> {
+            GnomeClient::shared().performance_degraded().await
+        }
+        pub async fn hold_power_profile(
+            profile: PowerProfile,
+            reason: &str,
+            application_id: &str,
+        ) -> Result<ProfileHold> {
+            GnomeClient::shared()
+                .hold_power_profile(profile, reason, application_id)
+                .await
+        }
+        pub async fn power_profile_changes() -> Result<EventStream<PowerProfile>> {
+            GnomeClient::shared().power_profile_changes().await
+        }
+    }
+
+    pub mod screenshot {
+        use std::fmt;
+        use std::path::{Path, PathBuf};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        use image::RgbaImage;
+
+        use crate::{Error, GnomeClient, Result};
+
+        /// ## Color picked on the screen
+        /// Channels are between 0.0 and 1.0, as returned by GNOME Shell.
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct Color {
+            pub red: f64,
+            pub green: f64,
+            pub blue: f64,
+        }
+
+        impl Color {
+            pub fn new(red: f64, green: f64, blue: f64) -> Color {
+                Color {
+                    red: red.clamp(0.0, 1.0),
+                    green: green.clamp(0.0, 1.0),
+                    blue: blue.clamp(0.0, 1.0),
+                }
+            }
+            /// ## Parse `#rrggbb` or `rrggbb`
+            pub fn from_hex(hex: &str) -> Result<Color> {
+                let digits = hex.trim().trim_start_matches('#');
+                let channel = |index: usize| {
+                    digits
+                        .get(index..index + 2)
+                        .and_then(|channel| u8::from_str_radix(channel, 16).ok())
+                        .ok_or_else(|| Error::InvalidValue(format!("Invalid hex color {}", hex)))
+                };
+                if digits.len() != 6 {
+                    return Err(Error::InvalidValue(format!("Invalid hex color {}", hex)));
+                }
+                Ok(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+            }
+            pub fn from_rgb8(red: u8, green: u8, blue: u8) -> Color {
+                Color::new(
+                    red as f64 / 255.0,
+                    green as f64 / 255.0,
+                    blue as f64 / 255.0,
+                )
+            }
+            pub fn to_rgb8(&self) -> (u8, u8, u8) {
+                let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+                (channel(self.red), channel(self.green), channel(self.blue))
+            }
+            /// ## Lowercase `#rrggbb`
+            pub fn to_hex(&self) -> String {
+                let (red, green, blue) = self.to_rgb8();
+                format!("#{:02x}{:02x}{:02x}", red, green, blue)
+            }
+            /// ## `rgb(r, g, b)` with 8-bit channels
+            pub fn to_css(&self) -> String {
+                let (red, green, blue) = self.to_rgb8();
+                format!("rgb({}, {}, {})", red, green, blue)
+            }
+            /// ## `hsl(h, s%, l%)`
+            pub fn to_css_hsl(&self) -> String {
+                let (hue, saturation, lightness) = self.to_hsl();
+                format!(
+                    "hsl({}, {}%, {}%)",
+                    hue.round(),
+                    (saturation * 100.0).round(),
+                    (lightness * 100.0).round()
+                )
+            }
+            fn hue(&self, max: f64, delta: f64) -> f64 {
+                if delta == 0.0 {
+                    return 0.0;
+                }
+                let hue = if max == self.red {
+                    ((self.green - self.blue) / delta).rem_euclid(6.0)
+                } else if max == self.green {
+                    (self.blue - self.red) / delta + 2.0
+                } else {
+                    (self.red - self.green) / delta + 4.0
+                };
+                hue * 60.0
+            }
+            /// ## Hue in degrees (0–360), saturation and lightness (0–1)
+            pub fn to_hsl(&self) -> (f64, f64, f64) {
+                let max = self.red.max(self.green).max(self.blue);
+                let min = self.red.min(self.green).min(self.blue);
+                let delta = max - min;
+                let lightness = (max + min) / 2.0;
+                let saturation = if delta == 0.0 {
+                    0.0
+                } else {
+                    delta / (1.0 - (2.0 * lightness - 1.0).abs())
+                };
+                (self.hue(max, delta), saturation, lightness)
+            }
+            /// ## Hue in degrees (0–360), saturation and value (0–1)
+            pub fn to_hsv(&self) -> (f64, f64, f64) {
+                let max = self.red.max(self.green).max(self.blue);
+                let min = self.red.min(self.green).min(self.blue);
+                let delta = max - min;
+                let saturation = if max == 0.0 { 0.0 } else { delta / max };
+                (self.hue(max, delta), saturation, max)
+            }
+        }
+
+        impl fmt::Display for Color {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.to_hex())
+            }
+        }
+
+        /// ## Recently picked colors, newest first
+        /// Stored as one hex color per line, so channels are kept with 8-bit precision.
+        #[derive(Debug, Clone)]
+        pub struct ColorHistory {
+            path: PathBuf,
+            limit: usize,
+            colors: Vec<Color>,
+        }
+
+        impl ColorHistory {
+            /// ## History stored in `$XDG_DATA_HOME/gnome-dbus-api/colors`
+            pub fn default_path() -> PathBuf {
+                gio::glib::user_data_dir()
+                    .join("gnome-dbus-api")
+                    .join("colors")
+            }
+            /// ## Load the history, a missing file is an empty history
+            pub fn open(path: impl Into<PathBuf>) -> Result<ColorHistory> {
+                let path = path.into();
+                let colors = match std::fs::read_to_string(&path) {
+                    Ok(content) => content
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .map(Color::from_hex)
+                        .collect::<Result<Vec<Color>>>()?,
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                    Err(error) => return Err(error.into()),
+                };
+                let mut history = ColorHistory {
+                    path,
+                    limit: 20,
+                    colors,
+                };
+                history.colors.truncate(history.limit);
+                Ok(history)
+            }
+            /// ## Keep at most `limit` colors (20 by default)
+            pub fn with_limit(mut self, limit: usize) -> ColorHistory {
+                self.limit = limit;
+                self.colors.truncate(limit);
+                self
+            }
+            pub fn colors(&self) -> &[Color] {
+                &self.colors
+            }
+            /// ## Add a color on top and save the file
+            /// A color already in the history is moved to the top.
+            pub fn push(&mut self, color: Color) -> Result<()> {
+                let hex = color.to_hex();
+                self.colors.retain(|picked| picked.to_hex() != hex);
+                self.colors.insert(0, color);
+                self.colors.truncate(self.limit);
+                self.save()
+            }
+            pub fn clear(&mut self) -> Result<()> {
+                self.colors.clear();
+                self.save()
+            }
+            fn save(&self) -> Result<()> {
+                if let Some(parent) = self.path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let content: String = self
+                    .colors
+                    .iter()
+                    .map(|color| format!("{}\n", color.to_hex()))
+                    .collect();
+                std::fs::write(&self.path, content)?;
+                Ok(())
+            }
+        }
+
+        /// ## Rectangle on the screen, in logical pixels
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub struct Area {
+            pub x: i32,
+            pub y: i32,
+            pub width: i32,
+            pub height: i32,
+        }
+
+        /// ## What to capture
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Target {
+            Screen,
+            /// The focused window
+            Window {
+                include_frame: bool,
+            },
+            Area(Area),
+        }
+
+        /// ## Screenshot options
+        /// Without an output path the capture is written to a file in the temp directory.
+        #[derive(Debug, Clone, Default)]
+        pub struct ScreenshotOptions {
+            pub include_cursor: bool,
+            pub flash: bool,
+            pub output: Option<PathBuf>,
+        }
+
+        impl ScreenshotOptions {
+            pub fn new() -> ScreenshotOptions {
+                ScreenshotOptions::default()
+            }
+            pub fn include_cursor(mut self, include_cursor: bool) -> ScreenshotOptions {
+                self.include_cursor = include_cursor;
+                self
+            }
+            pub fn flash(mut self, flash: bool) -> ScreenshotOptions {
+                self.flash = flash;
+                self
+            }
+            pub fn output(mut self, output: impl Into<PathBuf>) -> ScreenshotOptions {
+                self.output = Some(output.into());
+                self
+            }
+        }
+
+        fn temp_file() -> PathBuf {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            std::env::temp_dir().join(format!(
+                "gnome-dbus-api-screenshot-{}-{}.png",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ))
+        }
+
+        fn saved((success, filename): (bool, String)) -> Result<PathBuf> {
+            if !success {
+                return Err(Error::Process(format!(
+                    "Unable to save the screenshot to {}",
+                    filename
+                )));
+            }
+            Ok(PathBuf::from(filename))
+        }
+
+        fn load(path: &Path) -> Result<RgbaImage> {
+            Ok(image::open(path)
+                .map_err(|error| Error::Parse(format!("{}: {}", path.display(), error)))?
+                .to_rgba8())
+        }
+
+        impl GnomeClient {
+            /// ## Let the user pick a color on the screen
+            /// `None` when the user cancelled with Escape.
+            pub async fn pick_color(&self) -> Result<Option<Color>> {
+                let color = self.screenshot_proxy().await?.pick_color().await?;
+                Ok(color.map(|(red, green, blue)| Color::new(red, green, blue)))
+            }
+            /// ## Pick a color and add it to the history
+            pub async fn pick_color_with_history(
+                &self,
+                history: &mut ColorHistory,
+            ) -> Result<Option<Color>> {
+                let color = self.pick_color().await?;
+                if let Some(color) = color {
+                    history.push(color)?;
+                }
+                Ok(color)
+            }
+            /// ## Capture a target and return the path of the saved file
+            pub async fn screenshot_target(
+                &self,
+                target: Target,
+                options: &ScreenshotOptions,
+            ) -> Result<PathBuf> {
+                let proxy = self.screenshot_proxy().await?;
+                let filename = options
+                    .output
+                    .clone()
+                    .unwrap_or_else(temp_file)
+                    .to_string_lossy()
+                    .to_string();
+                let reply = match target {
+                    Target::Screen => {
+                        proxy
+                            .Screenshot(options.include_cursor, options.flash, filename)
+                            .await?
+                    }
+                    Target::Window { include_frame } => {
+                        proxy
+                            .ScreenshotWindow(
+                                include_frame,
+                                options.include_cursor,
+                                options.flash,
+                                filename,
+                            )
+                            .await?
+                    }
+                    Target::Area(area) => {
+                        proxy
+                            .ScreenshotArea(
+                                area.x,
+                                area.y,
+                                area.width,
+                                area.height,
+                                options.flash,
+                                filename,
+                            )
+                            .await?
+                    }
+                };
+                saved(reply)
+            }
+            pub async fn screenshot(&self, options: &ScreenshotOptions) -> Result<PathBuf> {
+                self.screenshot_target(Target::Screen, options).await
+            }
+            pub async fn screenshot_window(
+                &self,
+                include_frame: bool,
+                options: &ScreenshotOptions,
+            ) -> Result<PathBuf> {
+                self.screenshot_target(Target::Window { include_frame }, options)
+                    .await
+            }
+            pub async fn screenshot_area(
+                &self,
+                area: Area,
+                options: &ScreenshotOptions,
+            ) -> Result<PathBuf> {
+                self.screenshot_target(Target::Area(area), options).await
+            }
+            /// ## Capture a target as an image
+            /// The file is removed afterwards unless an output path was given.
+            pub async fn capture_image(
+                &self,
+                target: Target,
+                options: &ScreenshotOptions,
+            ) -> Result<RgbaImage> {
+                let path = self.screenshot_target(target, options).await?;
+                let image = load(&path);
+                if options.output.is_none() {
+                    let _ = std::fs::remove_file(&path);
+                }
+                image
+            }
+            /// ## Let the user draw a rectangle on the screen
+            pub async fn select_area(&self) -> Result<Area> {
+                let (x, y, width, height) = self.screenshot_proxy().await?.SelectArea().await?;
+                Ok(Area {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+            pub async fn flash_area(&self, area: Area) -> Result<()> {
+                self.screenshot_proxy()
+                    .await?
+                    .FlashArea(area.x, area.y, area.width, area.height)
+                    .await?;
+                Ok(())
+            }
+        }
+
+        pub async fn pick_color() -> Result<Option<Color>> {
+            GnomeClient::shared().pick_color().await
+        }
+        pub async fn pick_color_with_history(history: &mut ColorHistory) -> Result<Option<Color>> {
+            GnomeClient::shared().pick_color_with_history(history).await
+        }
+        pub async fn screenshot(options: &ScreenshotOptions) -> Result<PathBuf> {
+            GnomeClient::shared().screenshot(options).await
+        }
+        pub async fn screenshot_window(
+            include_frame: bool,
+            options: &ScreenshotOptions,
+        ) -> Result<PathBuf> {
+            GnomeClient::shared()
+                .screenshot_window(include_frame, options)
+                .await
+        }
+        pub async fn screenshot_area(area: Area, options: &ScreenshotOptions) -> Result<PathBuf> {
+            GnomeClient::shared().screenshot_area(area, options).await
+        }
+        pub async fn capture_image(
+            target: Target,
+            options: &ScreenshotOptions,
+        ) -> Result<RgbaImage> {
+            GnomeClient::shared().capture_image(target, options).await
+        }
+        pub async fn select_area() -> Result<Area> {
+            GnomeClient::shared().select_area().await
+        }
+        pub async fn flash_area(area: Area) -> Result<()> {
+            GnomeClient::shared().flash_area(area).await
+        }
+    }
+
+    pub mod screencast {
+        use std::collections::HashMap;
+        use std::path::PathBuf;
+        use std::sync::{MutexGuard, PoisonError};
+
+        use zbus::names::OwnedUniqueName;
+
+        use super::screenshot::Area;
+        use crate::client::name_owner;
+        use crate::{Error, GnomeClient, Result};
+
+        const SCREENCAST_SERVICE: &str = "org.gnome.Shell.Screencast";
+
+        /// Recording started by a client, with the screencast service that runs it
+        #[derive(Debug)]
+        pub(crate) struct Recording {
+            path: PathBuf,
+            owner: Option<OwnedUniqueName>,
+        }
+
+        /// ## Screencast options
+        /// Unset options keep the GNOME Shell defaults (30 fps, cursor drawn, VP8/WebM).
+        /// Without a file template the recording goes to the Videos directory.
+        #[derive(Debug, Clone, Default)]
+        pub struct ScreencastOptions {
+            /// `%d` is replaced by the date, `%t` by the time, and the extension matching
+            /// the pipeline is appended. Relative templates are saved in the Videos directory.
+            pub file_template: Option<String>,
+            pub framerate: Option<i32>,
+            pub draw_cursor: Option<bool>,
+            /// GStreamer pipeline encoding the frames, e.g. `vp8enc ! queue ! webmmux`
+            pub pipeline: Option<String>,
+        }
+
+        impl ScreencastOptions {
+            pub fn new() -> ScreencastOptions {
+                ScreencastOptions::default()
+            }
+            pub fn file_template(mut self, file_template: impl Into<String>) -> ScreencastOptions {
+                self.file_template = Some(file_template.into());
+                self
+            }
+            pub fn framerate(mut self, framerate: i32) -> ScreencastOptions {
+                self.framerate = Some(framerate);
+                self
+            }
+            pub fn draw_cursor(mut self, draw_cursor: bool) -> ScreencastOptions {
+                self.draw_cursor = Some(draw_cursor);
+                self
+            }
+            pub fn pipeline(mut self, pipeline: impl Into<String>) -> ScreencastOptions {
+                self.pipeline = Some(pipeline.into());
+                self
+            }
+            fn file_template_or_default(&self) -> &str {
+                self.file_template
+                    .as_deref()
+                    .unwrap_or("Screencast from %d %t")
+            }
+            fn to_dict(&self) -> Result<HashMap<&str, zvariant::Value<'_>>> {
+                let mut options = HashMap::new();
+                if let Some(framerate) = self.framerate {
+                    if framerate <= 0 {
+                        return Err(Error::InvalidValue(format!(
+                            "Framerate must be positive, got {}",
+                            framerate
+                        )));
+                    }
+                    options.insert("framerate", zvariant::Value::from(framerate));
+                }
+                if let Some(draw_cursor) = self.draw_cursor {
+                    options.insert("draw-cursor", zvariant::Value::from(draw_cursor));
+                }
+                if let Some(pipeline) = &self.pipeline {
+                    options.insert("pipeline", zvariant::Value::from(pipeline.as_str()));
+                }
+                Ok(options)
+            }
+        }
+
+        impl GnomeClient {
+            /// ## Record the whole screen
+            /// GNOME Shell stops the recording when the connection of the client closes,
+            /// keep the client alive until [`GnomeClient::stop_screencast`].
+            pub async fn start_screencast(&self, options: &ScreencastOptions) -> Result<PathBuf> {
+                self.start_recording(None, options).await
+            }
+            /// ## Record an area of the screen
+            pub async fn start_screencast_area(
+                &self,
+                area: Area,
+                options: &ScreencastOptions,
+            ) -> Result<PathBuf> {
+                self.start_recording(Some(area), options).await
+            }
+            async fn start_recording(
+                &self,
+                area: Option<Area>,
+                options: &ScreencastOptions,
+            ) -> Result<PathBuf> {
+                if self.is_recording().await? {
+                    return Err(Error::Process(
+                        "A screencast is already running".to_string(),
+                    ));
+                }
+                let proxy = self.screencast_proxy().await?;
+                let dict = options.to_dict()?;
+                let template = options.file_template_or_default();
+                let (success, filename) = match area {
+                    None => proxy.Screencast(template, dict).await?,
+                    Some(area) => {
+                        proxy
+                            .ScreencastArea(area.x, area.y, area.width, area.height, template, dict)
+                            .await?
+                    }
+                };
+                if !success {
+                    return Err(Error::Process(
+                        "GNOME Shell refused to start the screencast".to_string(),
+                    ));
+                }
+                let owner = name_owner(self.session().await?, SCREENCAST_SERVICE).await?;
+                let path = PathBuf::from(filename);
+                *self.lock_recording() = Some(Recording {
+                    path: path.clone(),
+                    owner,
+                });
+                Ok(path)
+            }
+            /// ## Stop the running recording and return its file
+            /// `None` when nothing was recording.
+            pub async fn stop_screencast(&self) -> Result<Option<PathBuf>> {
+                if !self.is_recording().await? {
+                    return Ok(None);
+                }
+                let stopped = self.screencast_proxy().await?.StopScreencast().await?;
+                let path = self.lock_recording().take().map(|recording| recording.path);
+                if !stopped {
+                    return Err(Error::Process(
+                        "GNOME Shell was unable to stop the screencast".to_string(),
+                    ));
+                }
+                Ok(path)
+            }
+            /// ## Whether a recording started by this client is running
+            /// `false` once the screencast service that ran it is gone,
+            /// e.g. after a shell restart or when the shell ended it on its own.
+            pub async fn is_recording(&self) -> Result<bool> {
+                Ok(self.recording().await?.is_some())
+            }
+            /// ## File of the running recording
+            pub async fn recording(&self) -> Result<Option<PathBuf>> {
+                let owner = name_owner(self.session().await?, SCREENCAST_SERVICE).await?;
+                let mut recording = self.lock_recording();
+                if matches!(&*recording, Some(running) if running.owner != owner) {
+                    *recording = None;
+                }
+                Ok(recording.as_ref().map(|running| running.path.clone()))
+            }
+            /// The state is a plain value, still valid after a panic of another thread
+            fn lock_recording(&self) -> MutexGuard<'_, Option<Recording>> {
+                self.recording
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+            }
+        }
+
+        pub async fn start_screencast(options: &ScreencastOptions) -> Result<PathBuf> {
+            GnomeClient::shared().start_screencast(options).await
+        }
+        pub async fn start_screencast_area(
+            area: Area,
+            options: &ScreencastOptions,
+        ) -> Result<PathBuf> {
+            GnomeClient::shared()
+                .start_screencast_area(area, options)
+                .await
+        }
+        pub async fn stop_screencast() -> Result<Option<PathBuf>> {
+            GnomeClient::shared().stop_screencast().await
+        }
+        pub async fn is_recording() -> Result<bool> {
+            GnomeClient::shared().is_recording().await
+        }
+        pub async fn recording() -> Result<Option<PathBuf>> {
+            GnomeClient::shared().recording().await
+        }
+    }
+
+    pub mod screen {
+        use std::fmt;
+
+        use futures_util::StreamExt;
+
+        use super::ScreenProxy;
+        use crate::{Error, EventStream, GnomeClient, Result};
+
+        /// ## Screen brightness, a percentage between 0 and 100
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        pub struct Brightness(u8);
+
+        impl Brightness {
+            pub const MIN: Brightness = Brightness(0);
+            pub const MAX: Brightness = Brightness(100);
+
+            pub fn new(percent: i32) -> Result<Brightness> {
+                match u8::try_from(percent) {
+                    Ok(percent) if percent <= 100 => Ok(Brightness(percent)),
+                    _ => Err(Error::InvalidValue(format!(
+                        "Brightness must be between 0 and 100, got {}",
+                        percent
+                    ))),
+                }
+            }
+            pub fn percent(&self) -> u8 {
+                self.0
+            }
+            /// Value read from gnome-settings-daemon, -1 when there is no backlight
+            pub(crate) fn from_reply(value: i32) -> Result<Brightness> {
+                if value == -1 {
+                    return Err(Error::Unsupported(
+                        "No backlight to control on this machine".to_string(),
+                    ));
+                }
+                Brightness::new(value)
+                    .map_err(|_| Error::Parse(format!("Invalid brightness {}", value)))
+            }
+        }
+
+        impl TryFrom<i32> for Brightness {
+            type Error = Error;
+
+            fn try_from(percent: i32) -> Result<Brightness> {
+                Brightness::new(percent)
+            }
+        }
+
+        impl From<Brightness> for i32 {
+            fn from(brightness: Brightness) -> i32 {
+                brightness.0 as i32
+            }
+        }
+
+        impl fmt::Display for Brightness {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}%", self.0)
+            }
+        }
+
+        impl GnomeClient {
+            /// ## Current brightness
+            /// `Error::Unsupported` when the machine has no backlight.
+            pub async fn brightness(&self) -> Result<Brightness> {
+                Brightness::from_reply(self.screen_proxy().await?.Brightness().await?)
+            }
+            pub async fn set_brightness(&self, brightness: Brightness) -> Result<()> {
+                self.screen_proxy()
+                    .await?
+                    .set_Brightness(brightness.into())
+                    .await?;
+                Ok(())
+            }
+            pub async fn brightness_step_up(&self) -> Result<()> {
+                self.screen_proxy().await?.StepUp().await?;
+                Ok(())
+            }
+            pub async fn brightness_step_down(&self) -> Result<()> {
+                self.screen_proxy().await?.StepDown().await?;
+                Ok(())
+            }
+            /// ## Stream of brightness changes, hardware keys included
+            pub async fn brightness_changes(&self) -> Result<EventStream<Brightness>> {
+                let proxy = ScreenProxy::new(self.session().await?).await?;
+                Ok(proxy
+                    .receive_Brightness_changed()
+                    .await
+                    .then(|change| async move { Brightness::from_reply(change.get().await?) })
+                    .boxed())
+            }
         }
-    }
 
-    #[dbus_proxy(
-        interface = "org.gnome.SettingsDaemon.Power.Screen",
-        default_service = "org.gnome.SettingsDaemon.Power",
-        default_path = "/org/gnome/SettingsDaemon/Power"
-    )]
-    trait Screen {
-        #[dbus_proxy(property)]
-        fn Brightness(&self) -> Result<i32>;
-        #[dbus_proxy(property)]
-        fn set_Brightness(&self, brightness: i32) -> Result<()>;
-        fn StepUp(&self) -> Result<()>;
-        fn StepDown(&self) -> Result<()>;
+        pub async fn brightness() -> Result<Brightness> {
+            GnomeClient::shared().brightness().await
+        }
+        pub async fn set_brightness(brightness: Brightness) -> Result<()> {
+            GnomeClient::shared().set_brightness(brightness).await
+        }
+        pub async fn step_up() -> Result<()> {
+            GnomeClient::shared().brightness_step_up().await
+        }
+        pub async fn step_down() -> Result<()> {
+            GnomeClient::shared().brightness_step_down().await
+        }
+        pub async fn brightness_changes() -> Result<EventStream<Brightness>> {
+            GnomeClient::shared().brightness_changes().await
+        }
     }
 
-    pub mod power {
-        use zbus::Connection;
+    pub mod keyboard_backlight {
+        use futures_util::{FutureExt, StreamExt};
+        use zbus::dbus_proxy;
 
-        use crate::handlers::easy_gnome::PowerManagementProxy;
+        use super::screen::Brightness;
+        use crate::client::name_has_owner;
+        use crate::{Error, EventStream, GnomeClient, Result};
 
-        use super::{PowerProfile, PowerProfilesProxy};
+        const SETTINGS_DAEMON: &str = "org.gnome.SettingsDaemon.Power";
 
-        pub async fn power_off() {
-            let connection = Connection::system().await.unwrap();
-            let proxy = PowerManagementProxy::new(&connection).await.unwrap();
-            proxy.PowerOff(true).await.unwrap();
-        }
-        pub async fn suspend() {
-            let connection = Connection::system().await.unwrap();
-            let proxy = PowerManagementProxy::new(&connection).await.unwrap();
-            proxy.Suspend(true).await.unwrap();
-        }
-        pub async fn reboot() {
-            let connection = Connection::system().await.unwrap();
-            let proxy = PowerManagementProxy::new(&connection).await.unwrap();
-            proxy.Reboot(true).await.unwrap();
-        }
-        pub async fn get_power_profile() -> PowerProfile {
-            let connection = Connection::system().await.unwrap();
-            let proxy = PowerProfilesProxy::new(&connection).await.unwrap();
-            PowerProfile::from(proxy.ActiveProfile().await.unwrap().as_str())
-        }
-        pub async fn set_power_profile(profile: PowerProfile) {
-            let connection = Connection::system().await.unwrap();
-            let proxy = PowerProfilesProxy::new(&connection).await.unwrap();
-            proxy
-                .set_ActiveProfile(profile.as_str().to_string())
-                .await
-                .unwrap();
+        #[dbus_proxy(
+            interface = "org.gnome.SettingsDaemon.Power.Keyboard",
+            default_service = "org.gnome.SettingsDaemon.Power",
+            default_path = "/org/gnome/SettingsDaemon/Power"
+        )]
+        pub(crate) trait Keyboard {
+            #[dbus_proxy(property)]
+            fn Brightness(&self) -> zbus::Result<i32>;
+            #[dbus_proxy(property)]
+            fn set_Brightness(&self, brightness: i32) -> zbus::Result<()>;
+            fn StepUp(&self) -> zbus::Result<i32>;
+            fn StepDown(&self) -> zbus::Result<i32>;
+            fn Toggle(&self) -> zbus::Result<i32>;
+            #[dbus_proxy(signal)]
+            fn BrightnessChanged(&self, brightness: i32, source: &str) -> zbus::Result<()>;
         }
-    }
-
-    pub mod screenshot {
-        use zbus::Connection;
 
-        use crate::handlers::easy_gnome::ScreenshotProxy;
+        #[dbus_proxy(
+            interface = "org.freedesktop.UPower.KbdBacklight",
+            default_service = "org.freedesktop.UPower",
+            default_path = "/org/freedesktop/UPower/KbdBacklight"
+        )]
+        pub(crate) trait KbdBacklight {
+            fn GetMaxBrightness(&self) -> zbus::Result<i32>;
+            fn GetBrightness(&self) -> zbus::Result<i32>;
+            fn SetBrightness(&self, value: i32) -> zbus::Result<()>;
+            #[dbus_proxy(signal)]
+            fn BrightnessChanged(&self, value: i32) -> zbus::Result<()>;
+        }
 
-        pub async fn pick_color() -> (f64, f64, f64) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ScreenshotProxy::new(&connection).await.unwrap();
-            proxy.pick_color().await
+        /// ## Service controlling the keyboard backlight
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Backend {
+            /// `org.gnome.SettingsDaemon.Power.Keyboard` on the session bus, levels in percent
+            SettingsDaemon,
+            /// `org.freedesktop.UPower.KbdBacklight` on the system bus, raw hardware levels
+            UPower,
         }
-    }
 
-    pub mod screen {
-        use zbus::Connection;
+        /// UPower levels go from 0 to a hardware maximum, usually 2 or 3
+        fn to_percent(value: i32, max: i32) -> Result<Brightness> {
+            if max <= 0 {
+                return Err(Error::Unsupported(
+                    "No keyboard backlight on this machine".to_string(),
+                ));
+            }
+            Brightness::new((value.clamp(0, max) * 100 + max / 2) / max)
+        }
+        fn to_level(brightness: Brightness, max: i32) -> i32 {
+            (i32::from(brightness) * max + 50) / 100
+        }
 
-        use crate::handlers::easy_gnome::ScreenProxy;
+        impl GnomeClient {
+            /// ## gnome-settings-daemon when it runs, UPower otherwise
+            /// Detected again once gnome-settings-daemon starts or stops.
+            pub async fn keyboard_backlight_backend(&self) -> Result<Backend> {
+                let mut cached = self.keyboard_backend.lock().await;
+                if let Some((backend, owner_changes)) = &mut *cached {
+                    // Pending means no `NameOwnerChanged` since the detection
+                    if owner_changes.next().now_or_never().is_none() {
+                        return Ok(*backend);
+                    }
+                }
+                let session = self.session().await?;
+                let owner_changes = zbus::fdo::DBusProxy::new(session)
+                    .await?
+                    .receive_name_owner_changed_with_args(&[(0, SETTINGS_DAEMON)])
+                    .await?;
+                let backend = if name_has_owner(session, SETTINGS_DAEMON).await? {
+                    Backend::SettingsDaemon
+                } else {
+                    Backend::UPower
+                };
+                *cached = Some((backend, owner_changes));
+                Ok(backend)
+            }
+            /// UPower answers 0 or -1 when there is no backlight
+            async fn kbd_backlight_max(&self) -> Result<(&KbdBacklightProxy<'static>, i32)> {
+                let proxy = self.kbd_backlight_proxy().await?;
+                let max = proxy.GetMaxBrightness().await?;
+                if max <= 0 {
+                    return Err(Error::Unsupported(
+                        "No keyboard backlight on this machine".to_string(),
+                    ));
+                }
+                Ok((proxy, max))
+            }
+            async fn set_kbd_backlight_level(&self, level: i32) -> Result<Brightness> {
+                let (proxy, max) = self.kbd_backlight_max().await?;
+                let level = level.clamp(0, max);
+                proxy.SetBrightness(level).await?;
+                to_percent(level, max)
+            }
+            /// ## Current keyboard backlight level
+            /// `Error::Unsupported` when the keyboard has no backlight.
+            pub async fn keyboard_brightness(&self) -> Result<Brightness> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        Brightness::from_reply(self.keyboard_proxy().await?.Brightness().await?)
+                    }
+                    Backend::UPower => {
+                        let (proxy, max) = self.kbd_backlight_max().await?;
+                        to_percent(proxy.GetBrightness().await?, max)
+                    }
+                }
+            }
+            /// ## Set the keyboard backlight level
+            /// With UPower the level is rounded to the nearest hardware level.
+            pub async fn set_keyboard_brightness(&self, brightness: Brightness) -> Result<()> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        self.keyboard_proxy()
+                            .await?
+                            .set_Brightness(brightness.into())
+                            .await?;
+                    }
+                    Backend::UPower => {
+                        let (proxy, max) = self.kbd_backlight_max().await?;
+                        proxy.SetBrightness(to_level(brightness, max)).await?;
+                    }
+                }
+                Ok(())
+            }
+            /// ## Raise the backlight one step and return the new level
+            pub async fn keyboard_brightness_step_up(&self) -> Result<Brightness> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        Brightness::from_reply(self.keyboard_proxy().await?.StepUp().await?)
+                    }
+                    Backend::UPower => {
+                        let (proxy, _) = self.kbd_backlight_max().await?;
+                        let level = proxy.GetBrightness().await?;
+                        self.set_kbd_backlight_level(level + 1).await
+                    }
+                }
+            }
+            /// ## Lower the backlight one step and return the new level
+            pub async fn keyboard_brightness_step_down(&self) -> Result<Brightness> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        Brightness::from_reply(self.keyboard_proxy().await?.StepDown().await?)
+                    }
+                    Backend::UPower => {
+                        let (proxy, _) = self.kbd_backlight_max().await?;
+                        let level = proxy.GetBrightness().await?;
+                        self.set_kbd_backlight_level(level - 1).await
+                    }
+                }
+            }
+            /// ## Switch the backlight off, or back on, and return the new level
+            /// gnome-settings-daemon restores the previous level, UPower turns it fully on.
+            pub async fn toggle_keyboard_backlight(&self) -> Result<Brightness> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        Brightness::from_reply(self.keyboard_proxy().await?.Toggle().await?)
+                    }
+                    Backend::UPower => {
+                        let (proxy, max) = self.kbd_backlight_max().await?;
+                        let level = if proxy.GetBrightness().await? > 0 {
+                            0
+                        } else {
+                            max
+                        };
+                        self.set_kbd_backlight_level(level).await
+                    }
+                }
+            }
+            /// ## Stream of backlight changes, hardware keys included
+            pub async fn keyboard_brightness_changes(&self) -> Result<EventStream<Brightness>> {
+                match self.keyboard_backlight_backend().await? {
+                    Backend::SettingsDaemon => {
+                        let changes = self
+                            .keyboard_proxy()
+                            .await?
+                            .receive_BrightnessChanged()
+                            .await?;
+                        Ok(changes
+                            .map(|signal| Brightness::from_reply(signal.args()?.brightness))
+                            .boxed())
+                    }
+                    Backend::UPower => {
+                        let (proxy, max) = self.kbd_backlight_max().await?;
+                        let changes = proxy.receive_BrightnessChanged().await?;
+                        Ok(changes
+                            .map(move |signal| to_percent(signal.args()?.value, max))
+                            .boxed())
+                    }
+                }
+            }
+        }
 
-        pub async fn brightness() -> i32 {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ScreenProxy::new(&connection).await.unwrap();
-            proxy.Brightness().await.unwrap()
+        pub async fn backend() -> Result<Backend> {
+            GnomeClient::shared().keyboard_backlight_backend().await
+        }
+        pub async fn brightness() -> Result<Brightness> {
+            GnomeClient::shared().keyboard_brightness().await
+        }
+        pub async fn set_brightness(brightness: Brightness) -> Result<()> {
+            GnomeClient::shared()
+                .set_keyboard_brightness(brightness)
+                .await
         }
-        pub async fn set_brightness(brightness: i32) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ScreenProxy::new(&connection).await.unwrap();
-            proxy.set_Brightness(brightness).await.unwrap();
+        pub async fn step_up() -> Result<Brightness> {
+            GnomeClient::shared().keyboard_brightness_step_up().await
         }
-        pub async fn step_up() {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ScreenProxy::new(&connection).await.unwrap();
-            proxy.StepUp().await.unwrap();
+        pub async fn step_down() -> Result<Brightness> {
+            GnomeClient::shared().keyboard_brightness_step_down().await
         }
-        pub async fn step_down() {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ScreenProxy::new(&connection).await.unwrap();
-            proxy.StepDown().await.unwrap();
+        pub async fn toggle() -> Result<Brightness> {
+            GnomeClient::shared().toggle_keyboard_backlight().await
+        }
+        pub async fn brightness_changes() -> Result<EventStream<Brightness>> {
+            GnomeClient::shared().keyboard_brightness_changes().await
         }
     }
 
     pub mod nightlight {
+        use crate::dconf::Watch;
+        use crate::Result;
 
-        pub fn get_nightlight_active() -> bool {
+        pub fn get_nightlight_active() -> Result<bool> {
             crate::dconf::get(
                 "org.gnome.settings-daemon.plugins.color",
                 "night-light-enabled",
             )
-            .unwrap()
-            .parse::<bool>()
-            .unwrap()
         }
-        pub fn set_nightlight_active(active: bool) {
+        pub fn set_nightlight_active(active: bool) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.settings-daemon.plugins.color",
                 "night-light-enabled",
-                active.to_string().as_str(),
+                active,
             )
-            .unwrap();
         }
-        pub fn get_temperature() -> u32 {
+        pub fn get_temperature() -> Result<u32> {
             crate::dconf::get(
                 "org.gnome.settings-daemon.plugins.color",
                 "night-light-temperature",
             )
-            .unwrap()
-            .parse::<u32>()
-            .unwrap()
         }
-        pub fn reset_temperature() {
+        pub fn reset_temperature() -> Result<()> {
             crate::dconf::reset(
                 "org.gnome.settings-daemon.plugins.color",
                 "night-light-temperature",
             )
-            .unwrap();
         }
-        pub fn set_temperature(temperature: u32) {
+        pub fn set_temperature(temperature: u32) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.settings-daemon.plugins.color",
                 "night-light-temperature",
-                temperature.to_string().as_str(),
+                temperature,
+            )
+        }
+        pub fn watch_nightlight_active() -> Result<Watch<bool>> {
+            crate::dconf::watch(
+                "org.gnome.settings-daemon.plugins.color",
+                "night-light-enabled",
+            )
+        }
+        pub fn watch_temperature() -> Result<Watch<u32>> {
+            crate::dconf::watch(
+                "org.gnome.settings-daemon.plugins.color",
+                "night-light-temperature",
             )
-            .unwrap();
         }
     }
 
@@ -348,26 +2098,29 @@ pub mod easy_gnome {
             pub fn get_icon(&self) -> &Option<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>> {
                 &self.icon
             }
-            pub fn get_base64_icon(&self) -> Option<String> {
+            pub fn get_base64_icon(&self) -> crate::Result<Option<String>> {
                 match &self.icon {
                     Some(icon) => {
                         let mut image_data: Vec<u8> = Vec::new();
                         icon.write_to(&mut Cursor::new(&mut image_data), ImageOutputFormat::Png)
-                            .unwrap();
+                            .map_err(|error| crate::Error::Process(error.to_string()))?;
                         let res_base64 = base64::encode(image_data);
-                        Some(format!("data:image/png;base64,{}", res_base64))
+                        Ok(Some(format!("data:image/png;base64,{}", res_base64)))
                     }
-                    None => None,
+                    None => Ok(None),
                 }
             }
-            pub fn launch(&self) -> Result<(), gio::glib::Error> {
+            pub fn launch(&self) -> crate::Result<()> {
                 // Find app by name
                 let __apps = AppInfo::all();
                 __apps
                     .iter()
                     .find(|app| app.name().eq_ignore_ascii_case(&self.name))
-                    .unwrap()
+                    .ok_or_else(|| {
+                        crate::Error::InvalidValue(format!("App {} not found", self.name))
+                    })?
                     .launch(&[], None::<&gio::AppLaunchContext>)
+                    .map_err(|error| crate::Error::Process(error.to_string()))
             }
         }
 
@@ -380,268 +2133,1147 @@ pub mod easy_gnome {
                 &self.apps
             }
 
-            pub fn new() -> Apps {
-                const ICON_SIZE: i32 = 128;
-
+            /// ## Every app shown in the menus, with its icon when it can be loaded
+            /// Needs a display for the icon theme, after `gtk::init()`.
+            pub fn new() -> crate::Result<Apps> {
                 let __apps = AppInfo::all();
-                let icon_theme: IconTheme = IconTheme::default().unwrap();
-                icon_theme.add_resource_path(
-                    format!(
-                        "{}/.local/share/icons/hicolor",
-                        home_dir().to_str().unwrap()
-                    )
-                    .as_str(),
-                );
-                let mut apps: Vec<App> = Vec::new();
-
-                for app in &__apps {
-                    if !app.should_show() {
-                        continue;
-                    }
-                    let name = app.name();
-                    let description = app.description();
-                    let icon = app.icon();
-                    let executable = app.executable();
-
-                    if icon.is_none() {
-                        apps.push(App {
-                            name,
-                            description,
-                            icon: None,
-                            executable,
-                        });
-                        continue;
-                    }
-                    let icon_name = gio::prelude::IconExt::to_string(&icon.unwrap()).unwrap();
-                    // // Transform icon name to pixbuf
-                    let pixbuf = icon_theme
-                        .load_icon(&icon_name, ICON_SIZE, IconLookupFlags::GENERIC_FALLBACK)
-                        .unwrap_or(
-                            icon_theme
-                                .load_icon("info", ICON_SIZE, IconLookupFlags::GENERIC_FALLBACK)
-                                .unwrap(),
-                        );
-
-                    // Pix buf are cuadruplets of u8 (rgba)
-                    let bytes: Vec<u8> = pixbuf.unwrap().read_pixel_bytes().unwrap().to_vec();
-
-                    // Using image library build a png based on cuadruplets (rgba)
-                    let png: image::ImageBuffer<image::Rgba<u8>, Vec<u8>> =
-                        match image::RgbaImage::from_vec(ICON_SIZE as u32, ICON_SIZE as u32, bytes)
-                        {
-                            Some(png) => png,
-                            None => continue,
-                        };
-
-                    apps.push(App {
-                        name,
-                        description,
-                        icon: Some(png),
-                        executable,
-                    });
+                let icon_theme: IconTheme = IconTheme::default().ok_or_else(|| {
+                    crate::Error::ServiceUnavailable("No icon theme without a display".to_string())
+                })?;
+                if let Some(home) = home_dir().to_str() {
+                    icon_theme.add_resource_path(&format!("{}/.local/share/icons/hicolor", home));
                 }
-                Apps { apps }
+                let apps = __apps
+                    .iter()
+                    .filter(|app| app.should_show())
+                    .map(|app| App {
+                        name: app.name(),
+                        description: app.description(),
+                        icon: app.icon().and_then(|icon| load_icon(&icon_theme, &icon)),
+                        executable: app.executable(),
+                    })
+                    .collect();
+                Ok(Apps { apps })
             }
         }
+
+        /// Icon as a PNG, `None` when neither the icon nor the "info" fallback can be loaded
+        fn load_icon(icon_theme: &IconTheme, icon: &gio::Icon) -> Option<image::RgbaImage> {
+            const ICON_SIZE: i32 = 128;
+
+            let icon_name = gio::prelude::IconExt::to_string(icon)?;
+            // Transform icon name to pixbuf
+            let pixbuf = icon_theme
+                .load_icon(&icon_name, ICON_SIZE, IconLookupFlags::GENERIC_FALLBACK)
+                .or_else(|_| {
+                    icon_theme.load_icon("info", ICON_SIZE, IconLookupFlags::GENERIC_FALLBACK)
+                })
+                .ok()??;
+
+            // Pix buf are cuadruplets of u8 (rgba)
+            let bytes: Vec<u8> = pixbuf.read_pixel_bytes()?.to_vec();
+
+            // Using image library build a png based on cuadruplets (rgba)
+            image::RgbaImage::from_vec(ICON_SIZE as u32, ICON_SIZE as u32, bytes)
+        }
     }
 
     pub mod battery {
-        use upower_dbus::{DeviceProxy, UPowerProxy};
+        use std::collections::HashMap;
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        use futures_util::future::{join_all, ready};
+        use futures_util::{stream, StreamExt};
+        use serde::Serialize;
+        use zbus::names::InterfaceName;
+        use zbus::{dbus_proxy, CacheProperties, Connection};
+        use zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+
+        use crate::{Error, EventStream, GnomeClient, Result};
+
+        const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+        #[dbus_proxy(
+            interface = "org.freedesktop.UPower",
+            default_service = "org.freedesktop.UPower",
+            default_path = "/org/freedesktop/UPower"
+        )]
+        pub(crate) trait UPower {
+            fn EnumerateDevices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+            fn GetDisplayDevice(&self) -> zbus::Result<OwnedObjectPath>;
+            #[dbus_proxy(property)]
+            fn OnBattery(&self) -> zbus::Result<bool>;
+            #[dbus_proxy(property)]
+            fn LidIsClosed(&self) -> zbus::Result<bool>;
+            #[dbus_proxy(property)]
+            fn LidIsPresent(&self) -> zbus::Result<bool>;
+            #[dbus_proxy(signal)]
+            fn DeviceAdded(&self, device: ObjectPath<'_>) -> zbus::Result<()>;
+            #[dbus_proxy(signal)]
+            fn DeviceRemoved(&self, device: ObjectPath<'_>) -> zbus::Result<()>;
+        }
+
+        #[dbus_proxy(
+            interface = "org.freedesktop.UPower.Device",
+            default_service = "org.freedesktop.UPower",
+            default_path = "/org/freedesktop/UPower/devices/DisplayDevice"
+        )]
+        pub(crate) trait Device {
+            fn GetHistory(
+                &self,
+                kind: &str,
+                timespan: u32,
+                resolution: u32,
+            ) -> zbus::Result<Vec<(u32, f64, u32)>>;
+            fn GetStatistics(&self, kind: &str) -> zbus::Result<Vec<(f64, f64)>>;
+            fn EnableChargeThreshold(&self, enabled: bool) -> zbus::Result<()>;
+        }
+
+        /// ## Device plugged or unplugged, with its object path
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum DeviceEvent {
+            Added(String),
+            Removed(String),
+        }
+
+        /// ## Kind of device, the UPower `Type` property
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum DeviceKind {
+            Unknown,
+            LinePower,
+            Battery,
+            Ups,
+            Monitor,
+            Mouse,
+            Keyboard,
+            Pda,
+            Phone,
+            MediaPlayer,
+            Tablet,
+            Computer,
+            GamingInput,
+            Pen,
+            Touchpad,
+            Modem,
+            Network,
+            Headset,
+            Speakers,
+            Headphones,
+            /// Kind added after this crate, with its UPower number
+            Other(u32),
+        }
+
+        impl From<u32> for DeviceKind {
+            fn from(kind: u32) -> DeviceKind {
+                match kind {
+                    0 => DeviceKind::Unknown,
+                    1 => DeviceKind::LinePower,
+                    2 => DeviceKind::Battery,
+                    3 => DeviceKind::Ups,
+                    4 => DeviceKind::Monitor,
+                    5 => DeviceKind::Mouse,
+                    6 => DeviceKind::Keyboard,
+                    7 => DeviceKind::Pda,
+                    8 => DeviceKind::Phone,
+                    9 => DeviceKind::MediaPlayer,
+                    10 => DeviceKind::Tablet,
+                    11 => DeviceKind::Computer,
+                    12 => DeviceKind::GamingInput,
+                    13 => DeviceKind::Pen,
+                    14 => DeviceKind::Touchpad,
+                    15 => DeviceKind::Modem,
+                    16 => DeviceKind::Network,
+                    17 => DeviceKind::Headset,
+                    18 => DeviceKind::Speakers,
+                    19 => DeviceKind::Headphones,
+                    kind => DeviceKind::Other(kind),
+                }
+            }
+        }
+
+        /// ## Charge state, the UPower `State` property
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum BatteryState {
+            Unknown,
+            Charging,
+            Discharging,
+            Empty,
+            FullyCharged,
+            PendingCharge,
+            PendingDischarge,
+        }
+
+        impl From<u32> for BatteryState {
+            fn from(state: u32) -> BatteryState {
+                match state {
+                    1 => BatteryState::Charging,
+                    2 => BatteryState::Discharging,
+                    3 => BatteryState::Empty,
+                    4 => BatteryState::FullyCharged,
+                    5 => BatteryState::PendingCharge,
+                    6 => BatteryState::PendingDischarge,
+                    _ => BatteryState::Unknown,
+                }
+            }
+        }
+
+        /// ## Level computed by UPower from its own thresholds, the `WarningLevel` property
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum WarningLevel {
+            Unknown,
+            None,
+            /// Only for UPS
+            Discharging,
+            Low,
+            Critical,
+            /// The computer is about to shut down or hibernate
+            Action,
+        }
+
+        impl WarningLevel {
+            /// ## `Low`, `Critical` or `Action`
+            pub fn is_warning(&self) -> bool {
+                matches!(
+                    self,
+                    WarningLevel::Low | WarningLevel::Critical | WarningLevel::Action
+                )
+            }
+        }
+
+        impl From<u32> for WarningLevel {
+            fn from(level: u32) -> WarningLevel {
+                match level {
+                    1 => WarningLevel::None,
+                    2 => WarningLevel::Discharging,
+                    3 => WarningLevel::Low,
+                    4 => WarningLevel::Critical,
+                    5 => WarningLevel::Action,
+                    _ => WarningLevel::Unknown,
+                }
+            }
+        }
+
+        /// ## Battery chemistry, the UPower `Technology` property
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum BatteryTechnology {
+            Unknown,
+            LithiumIon,
+            LithiumPolymer,
+            LithiumIronPhosphate,
+            LeadAcid,
+            NickelCadmium,
+            NickelMetalHydride,
+        }
+
+        impl From<u32> for BatteryTechnology {
+            fn from(technology: u32) -> BatteryTechnology {
+                match technology {
+                    1 => BatteryTechnology::LithiumIon,
+                    2 => BatteryTechnology::LithiumPolymer,
+                    3 => BatteryTechnology::LithiumIronPhosphate,
+                    4 => BatteryTechnology::LeadAcid,
+                    5 => BatteryTechnology::NickelCadmium,
+                    6 => BatteryTechnology::NickelMetalHydride,
+                    _ => BatteryTechnology::Unknown,
+                }
+            }
+        }
+
+        /// ## Series recorded by UPower for `GetHistory`
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum HistoryKind {
+            /// Percentage
+            Charge,
+            /// Energy rate in W
+            Rate,
+            /// Seconds until full
+            TimeFull,
+            /// Seconds until empty
+            TimeEmpty,
+        }
 
-        // Get devices with battery stats
-        pub async fn get_current_device_battery() -> zbus::Result<DeviceProxy<'static>> {
-            let connection = zbus::Connection::system().await?;
-            let upower = UPowerProxy::new(&connection).await?;
-            let device: DeviceProxy<'_> = upower.get_display_device().await?;
-            Ok(device)
+        impl HistoryKind {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    HistoryKind::Charge => "charge",
+                    HistoryKind::Rate => "rate",
+                    HistoryKind::TimeFull => "time-full",
+                    HistoryKind::TimeEmpty => "time-empty",
+                }
+            }
+        }
+
+        /// ## One sample of a device history
+        #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+        pub struct HistoryPoint {
+            pub time: SystemTime,
+            pub value: f64,
+            pub state: BatteryState,
+        }
+
+        /// ## Profiles computed by UPower for `GetStatistics`
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum StatisticsKind {
+            Charging,
+            Discharging,
+        }
+
+        impl StatisticsKind {
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    StatisticsKind::Charging => "charging",
+                    StatisticsKind::Discharging => "discharging",
+                }
+            }
+        }
+
+        /// ## Charge or discharge profile at one percentage
+        #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+        pub struct StatisticsPoint {
+            /// Battery level, the index of the point in the series
+            pub percentage: u8,
+            pub value: f64,
+            /// Confidence in the value, in percent
+            pub accuracy: f64,
+        }
+
+        /// ## Snapshot of a UPower device, read with a single `GetAll` call
+        /// Energies are in Wh, rates in W, temperature in °C, voltage in V.
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct BatteryInfo {
+            /// D-Bus object path of the device
+            pub path: String,
+            pub native_path: String,
+            pub kind: DeviceKind,
+            pub state: BatteryState,
+            pub technology: BatteryTechnology,
+            pub warning_level: WarningLevel,
+            pub percentage: f64,
+            /// Seconds until empty, `None` when unknown
+            pub time_to_empty: Option<u64>,
+            /// Seconds until full, `None` when unknown
+            pub time_to_full: Option<u64>,
+            pub energy: f64,
+            pub energy_empty: f64,
+            pub energy_full: f64,
+            pub energy_full_design: f64,
+            pub energy_rate: f64,
+            /// Health, current full capacity against the design capacity in percent
+            pub capacity: f64,
+            pub temperature: f64,
+            pub voltage: f64,
+            pub model: String,
+            pub vendor: String,
+            pub serial: String,
+            /// `None` when the hardware does not report it
+            pub charge_cycles: Option<u32>,
+            pub is_present: bool,
+            pub is_rechargeable: bool,
+            /// Whether the device powers the computer (not a mouse or a headset)
+            pub power_supply: bool,
+        }
+
+        /// Property of a device, `None` when missing or of another type
+        fn property<T: TryFrom<OwnedValue>>(
+            properties: &HashMap<String, OwnedValue>,
+            name: &str,
+        ) -> Option<T> {
+            properties
+                .get(name)
+                .and_then(|value| T::try_from(value.clone()).ok())
+        }
+
+        impl BatteryInfo {
+            pub(crate) fn from_properties(
+                path: &str,
+                properties: &HashMap<String, OwnedValue>,
+            ) -> BatteryInfo {
+                let number = |name: &str| property::<f64>(properties, name).unwrap_or_default();
+                let text = |name: &str| property::<String>(properties, name).unwrap_or_default();
+                let flag = |name: &str| property::<bool>(properties, name).unwrap_or_default();
+                let seconds = |name: &str| {
+                    property::<i64>(properties, name)
+                        .filter(|seconds| *seconds > 0)
+                        .map(|seconds| seconds as u64)
+                };
+                let energy_full = number("EnergyFull");
+                let energy_full_design = number("EnergyFullDesign");
+                let capacity = match number("Capacity") {
+                    capacity if capacity > 0.0 => capacity,
+                    _ if energy_full_design > 0.0 => energy_full / energy_full_design * 100.0,
+                    _ => 0.0,
+                };
+                BatteryInfo {
+                    path: path.to_string(),
+                    native_path: text("NativePath"),
+                    kind: property::<u32>(properties, "Type")
+                        .unwrap_or_default()
+                        .into(),
+                    state: property::<u32>(properties, "State")
+                        .unwrap_or_default()
+                        .into(),
+                    technology: property::<u32>(properties, "Technology")
+                        .unwrap_or_default()
+                        .into(),
+                    warning_level: property::<u32>(properties, "WarningLevel")
+                        .unwrap_or_default()
+                        .into(),
+                    percentage: number("Percentage"),
+                    time_to_empty: seconds("TimeToEmpty"),
+                    time_to_full: seconds("TimeToFull"),
+                    energy: number("Energy"),
+                    energy_empty: number("EnergyEmpty"),
+                    energy_full,
+                    energy_full_design,
+                    energy_rate: number("EnergyRate"),
+                    capacity,
+                    temperature: number("Temperature"),
+                    voltage: number("Voltage"),
+                    model: text("Model"),
+                    vendor: text("Vendor"),
+                    serial: text("Serial"),
+                    charge_cycles: property::<i32>(properties, "ChargeCycles")
+                        .and_then(|cycles| u32::try_from(cycles).ok()),
+                    is_present: flag("IsPresent"),
+                    is_rechargeable: flag("IsRechargeable"),
+                    power_supply: flag("PowerSupply"),
+                }
+            }
+        }
+
+        /// ## Charge limit of a battery, to preserve its health on a laptop always plugged in
+        /// Read from the `ChargeThreshold*` properties of UPower 1.90 and later,
+        /// older versions report it as not supported.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+        pub struct ChargeLimit {
+            pub supported: bool,
+            pub enabled: bool,
+            /// Charging starts below this percentage when enabled
+            pub start: u32,
+            /// Charging stops at this percentage when enabled
+            pub end: u32,
+        }
+
+        impl ChargeLimit {
+            pub(crate) fn from_properties(properties: &HashMap<String, OwnedValue>) -> ChargeLimit {
+                ChargeLimit {
+                    supported: property(properties, "ChargeThresholdSupported").unwrap_or(false),
+                    enabled: property(properties, "ChargeThresholdEnabled").unwrap_or(false),
+                    start: property(properties, "ChargeStartThreshold").unwrap_or(0),
+                    end: property(properties, "ChargeEndThreshold").unwrap_or(100),
+                }
+            }
+        }
+
+        /// ## Percentages below which a device is reported as low, per kind of device
+        /// ```
+        /// use gnome_dbus_api::handlers::easy_gnome::battery::{DeviceKind, LowBatteryThresholds};
+        /// let thresholds = LowBatteryThresholds::new()
+        ///     .with_default(10.0)
+        ///     .with(DeviceKind::Mouse, 20.0);
+        /// assert_eq!(thresholds.threshold(DeviceKind::Mouse), Some(20.0));
+        /// assert_eq!(thresholds.threshold(DeviceKind::Battery), Some(10.0));
+        /// ```
+        #[derive(Debug, Clone, Default, PartialEq)]
+        pub struct LowBatteryThresholds {
+            default: Option<f64>,
+            kinds: HashMap<DeviceKind, f64>,
+        }
+
+        impl LowBatteryThresholds {
+            /// No threshold, only `WarningLevel` changes are reported
+            pub fn new() -> LowBatteryThresholds {
+                LowBatteryThresholds::default()
+            }
+            /// Threshold for the kinds without their own
+            pub fn with_default(mut self, percentage: f64) -> LowBatteryThresholds {
+                self.default = Some(percentage);
+                self
+            }
+            pub fn with(mut self, kind: DeviceKind, percentage: f64) -> LowBatteryThresholds {
+                self.kinds.insert(kind, percentage);
+                self
+            }
+            pub fn threshold(&self, kind: DeviceKind) -> Option<f64> {
+                self.kinds.get(&kind).copied().or(self.default)
+            }
+            /// Events between two snapshots of a device, `previous` is `None` for the first one
+            fn events(
+                &self,
+                previous: Option<&BatteryInfo>,
+                current: &BatteryInfo,
+            ) -> Vec<LowBatteryEvent> {
+                let event = |reason| LowBatteryEvent {
+                    path: current.path.clone(),
+                    model: current.model.clone(),
+                    kind: current.kind,
+                    percentage: current.percentage,
+                    reason,
+                };
+                let mut events = Vec::new();
+                if let Some(threshold) = self.threshold(current.kind) {
+                    let was_low =
+                        matches!(previous, Some(previous) if previous.percentage < threshold);
+                    let is_low = current.percentage < threshold;
+                    if is_low && !was_low {
+                        events.push(event(LowBatteryReason::Below(threshold)));
+                    } else if was_low && !is_low {
+                        events.push(event(LowBatteryReason::Recovered(threshold)));
+                    }
+                }
+                let level_changed = match previous {
+                    Some(previous) => previous.warning_level != current.warning_level,
+                    None => current.warning_level.is_warning(),
+                };
+                if level_changed {
+                    events.push(event(LowBatteryReason::WarningLevel(current.warning_level)));
+                }
+                events
+            }
+        }
+
+        /// ## Why a low battery event was emitted
+        #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub enum LowBatteryReason {
+            /// The percentage went below the threshold of the device kind
+            Below(f64),
+            /// The percentage went back above the threshold, e.g. while charging
+            Recovered(f64),
+            /// UPower changed the `WarningLevel` of the device
+            WarningLevel(WarningLevel),
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize)]
+        pub struct LowBatteryEvent {
+            pub path: String,
+            pub model: String,
+            pub kind: DeviceKind,
+            pub percentage: f64,
+            pub reason: LowBatteryReason,
+        }
+
+        /// The device left between `EnumerateDevices` and a read of its properties,
+        /// UPower answers `UnknownObject` or `UnknownMethod`
+        fn is_gone(error: &zbus::fdo::Error) -> bool {
+            match error {
+                zbus::fdo::Error::UnknownObject(_) | zbus::fdo::Error::UnknownMethod(_) => true,
+                zbus::fdo::Error::ZBus(zbus::Error::MethodError(name, _, _)) => matches!(
+                    name.as_str(),
+                    "org.freedesktop.DBus.Error.UnknownObject"
+                        | "org.freedesktop.DBus.Error.UnknownMethod"
+                ),
+                _ => false,
+            }
+        }
+
+        async fn properties_proxy(
+            connection: &Connection,
+            path: &str,
+        ) -> Result<zbus::fdo::PropertiesProxy<'static>> {
+            Ok(zbus::fdo::PropertiesProxy::builder(connection)
+                .destination("org.freedesktop.UPower")?
+                .path(path.to_string())?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?)
+        }
+
+        /// Read every property of a device in one call, `None` when there is no such device
+        async fn present_device_properties(
+            connection: &Connection,
+            path: &str,
+        ) -> Result<Option<HashMap<String, OwnedValue>>> {
+            match properties_proxy(connection, path)
+                .await?
+                .get_all(InterfaceName::from_static_str_unchecked(DEVICE_INTERFACE))
+                .await
+            {
+                Ok(properties) => Ok(Some(properties)),
+                Err(error) if is_gone(&error) => Ok(None),
+                Err(error) => Err(error.into()),
+            }
+        }
+
+        /// Read every property of a device in one call
+        pub(crate) async fn device_properties(
+            connection: &Connection,
+            path: &str,
+        ) -> Result<HashMap<String, OwnedValue>> {
+            present_device_properties(connection, path)
+                .await?
+                .ok_or_else(|| Error::ServiceUnavailable(format!("No UPower device at {}", path)))
+        }
+
+        /// Snapshots of a device, `None` when there is no such device
+        async fn property_changes(
+            connection: &Connection,
+            path: &str,
+        ) -> Result<Option<EventStream<BatteryInfo>>> {
+            let proxy = properties_proxy(connection, path).await?;
+            let changes = proxy.receive_properties_changed().await?;
+            let Some(properties) = present_device_properties(connection, path).await? else {
+                return Ok(None);
+            };
+            let path = path.to_string();
+            Ok(Some(
+                changes
+                    .scan(properties, move |properties, signal| {
+                        let snapshot = signal.args().map_err(Error::from).map(|args| {
+                            if args.interface_name().as_str() != DEVICE_INTERFACE {
+                                return None;
+                            }
+                            for (name, value) in args.changed_properties() {
+                                properties.insert(name.to_string(), OwnedValue::from(value));
+                            }
+                            Some(BatteryInfo::from_properties(&path, properties))
+                        });
+                        ready(Some(snapshot.transpose()))
+                    })
+                    .filter_map(ready)
+                    .boxed(),
+            ))
+        }
+
+        /// Low battery events of one device, until UPower reports its removal.
+        /// `None` when the device is already gone.
+        async fn low_battery_watch(
+            connection: &Connection,
+            upower: &UPowerProxy<'static>,
+            device: BatteryInfo,
+            thresholds: &LowBatteryThresholds,
+        ) -> Result<Option<EventStream<LowBatteryEvent>>> {
+            let path = device.path.clone();
+            let removed = upower
+                .receive_DeviceRemoved()
+                .await?
+                .filter(move |signal| {
+                    ready(matches!(signal.args(), Ok(args) if args.device.as_str() == path))
+                })
+                .boxed()
+                .into_future();
+            let Some(changes) = property_changes(connection, &device.path).await? else {
+                return Ok(None);
+            };
+            let initial = thresholds.events(None, &device);
+            let thresholds = thresholds.clone();
+            let events = changes
+                .scan(device, move |previous, current| {
+                    let events: Vec<Result<LowBatteryEvent>> = match current {
+                        Ok(current) => {
+                            let events = thresholds.events(Some(previous), &current);
+                            *previous = current;
+                            events.into_iter().map(Ok).collect()
+                        }
+                        Err(error) => vec![Err(error)],
+                    };
+                    ready(Some(stream::iter(events)))
+                })
+                .flatten();
+            Ok(Some(
+                stream::iter(initial.into_iter().map(Ok))
+                    .chain(events)
+                    .take_until(removed)
+                    .boxed(),
+            ))
+        }
+
+        /// Watch a device reported by `DeviceAdded`, `None` for devices without a battery
+        /// and for devices already gone
+        async fn added_device_watch(
+            connection: &Connection,
+            upower: &UPowerProxy<'static>,
+            path: &str,
+            thresholds: &LowBatteryThresholds,
+        ) -> Result<Option<EventStream<LowBatteryEvent>>> {
+            let Some(properties) = present_device_properties(connection, path).await? else {
+                return Ok(None);
+            };
+            let device = BatteryInfo::from_properties(path, &properties);
+            if !device.is_rechargeable {
+                return Ok(None);
+            }
+            low_battery_watch(connection, upower, device, thresholds).await
         }
-        pub async fn get_devices_battery() -> zbus::Result<Vec<DeviceProxy<'static>>> {
-            let connection = zbus::Connection::system().await?;
-            let upower = UPowerProxy::new(&connection).await?;
-            let devices: Vec<zvariant::OwnedObjectPath> = upower.enumerate_devices().await?;
 
-            let mut devices_battery: Vec<DeviceProxy<'_>> = Vec::new();
-            for device in devices {
-                let device_proxy = DeviceProxy::new(&connection, device).await?;
-                let is_rechargable = device_proxy.is_rechargeable().await?;
-                if is_rechargable {
-                    devices_battery.push(device_proxy);
+        impl GnomeClient {
+            /// ## Snapshot of any UPower device
+            pub async fn device_info(&self, path: &str) -> Result<BatteryInfo> {
+                let properties = device_properties(self.system().await?, path).await?;
+                Ok(BatteryInfo::from_properties(path, &properties))
+            }
+            /// ## Composite battery shown in the top bar
+            pub async fn current_device_battery(&self) -> Result<BatteryInfo> {
+                let path = self.upower_proxy().await?.GetDisplayDevice().await?;
+                self.device_info(path.as_str()).await
+            }
+            /// ## Every rechargeable device: laptop batteries, mice, headsets...
+            /// The devices are read concurrently. A device disconnected before its read is skipped.
+            pub async fn devices_battery(&self) -> Result<Vec<BatteryInfo>> {
+                let connection = self.system().await?;
+                let paths = self.upower_proxy().await?.EnumerateDevices().await?;
+                let reads = join_all(
+                    paths
+                        .iter()
+                        .map(|path| present_device_properties(connection, path.as_str())),
+                )
+                .await;
+                let mut devices = Vec::new();
+                for (path, read) in paths.iter().zip(reads) {
+                    if let Some(properties) = read? {
+                        let device = BatteryInfo::from_properties(path.as_str(), &properties);
+                        if device.is_rechargeable {
+                            devices.push(device);
+                        }
+                    }
+                }
+                Ok(devices)
+            }
+            async fn device_proxy(&self, path: &str) -> Result<DeviceProxy<'static>> {
+                Ok(DeviceProxy::builder(self.system().await?)
+                    .path(path.to_string())?
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?)
+            }
+            /// ## Samples of a device over the last `timespan`, newest first
+            /// `resolution` is the maximum number of points returned.
+            pub async fn battery_history(
+                &self,
+                path: &str,
+                kind: HistoryKind,
+                timespan: Duration,
+                resolution: u32,
+            ) -> Result<Vec<HistoryPoint>> {
+                let timespan = u32::try_from(timespan.as_secs()).unwrap_or(u32::MAX);
+                let points = self
+                    .device_proxy(path)
+                    .await?
+                    .GetHistory(kind.as_str(), timespan, resolution)
+                    .await?;
+                Ok(points
+                    .into_iter()
+                    .map(|(time, value, state)| HistoryPoint {
+                        time: UNIX_EPOCH + Duration::from_secs(time.into()),
+                        value,
+                        state: state.into(),
+                    })
+                    .collect())
+            }
+            /// ## Charge or discharge profile of a device, one point per percentage
+            pub async fn battery_statistics(
+                &self,
+                path: &str,
+                kind: StatisticsKind,
+            ) -> Result<Vec<StatisticsPoint>> {
+                let points = self
+                    .device_proxy(path)
+                    .await?
+                    .GetStatistics(kind.as_str())
+                    .await?;
+                Ok(points
+                    .into_iter()
+                    .zip(0..=u8::MAX)
+                    .map(|((value, accuracy), percentage)| StatisticsPoint {
+                        percentage,
+                        value,
+                        accuracy,
+                    })
+                    .collect())
+            }
+            /// ## Charge limit of a battery
+            /// The display device is composite, use the path of a battery from `devices_battery`.
+            pub async fn charge_limit(&self, path: &str) -> Result<ChargeLimit> {
+                let properties = device_properties(self.system().await?, path).await?;
+                Ok(ChargeLimit::from_properties(&properties))
+            }
+            /// ## Turn the charge limit of a battery on or off
+            /// Fails with `Error::Unsupported` when the battery or UPower cannot limit charging.
+            pub async fn set_charge_limit_enabled(&self, path: &str, enabled: bool) -> Result<()> {
+                if !self.charge_limit(path).await?.supported {
+                    return Err(Error::Unsupported(format!(
+                        "{} cannot limit its charge",
+                        path
+                    )));
+                }
+                Ok(self
+                    .device_proxy(path)
+                    .await?
+                    .EnableChargeThreshold(enabled)
+                    .await?)
+            }
+            /// ## Stream of low battery events for the rechargeable devices
+            /// Devices plugged in later are watched too, unplugged ones are dropped. Devices
+            /// already below their threshold or with a warning level are reported first.
+            pub async fn low_battery_events(
+                &self,
+                thresholds: LowBatteryThresholds,
+            ) -> Result<EventStream<LowBatteryEvent>> {
+                let connection = self.system().await?.clone();
+                let upower = self.upower_proxy().await?.clone();
+                // Subscribed before the listing, a device plugged in meanwhile is not missed
+                let added = upower.receive_DeviceAdded().await?;
+                let mut watches = Vec::new();
+                for device in self.devices_battery().await? {
+                    // `None` for a device unplugged since the listing
+                    watches.extend(
+                        low_battery_watch(&connection, &upower, device, &thresholds).await?,
+                    );
                 }
+                let added = added
+                    .then(move |signal| {
+                        let (connection, upower, thresholds) =
+                            (connection.clone(), upower.clone(), thresholds.clone());
+                        async move {
+                            let path = signal.args()?.device.to_string();
+                            added_device_watch(&connection, &upower, &path, &thresholds).await
+                        }
+                    })
+                    .filter_map(|watch| {
+                        ready(match watch {
+                            Ok(watch) => watch,
+                            Err(error) => Some(stream::once(ready(Err(error))).boxed()),
+                        })
+                    });
+                Ok(stream::iter(watches)
+                    .chain(added)
+                    .flatten_unordered(None)
+                    .boxed())
+            }
+            /// ## Whether the computer runs on battery
+            pub async fn on_battery(&self) -> Result<bool> {
+                Ok(self.upower_proxy().await?.OnBattery().await?)
+            }
+            pub async fn lid_is_closed(&self) -> Result<bool> {
+                Ok(self.upower_proxy().await?.LidIsClosed().await?)
+            }
+            /// ## Whether the computer has a lid, `false` on desktops
+            pub async fn lid_is_present(&self) -> Result<bool> {
+                Ok(self.upower_proxy().await?.LidIsPresent().await?)
             }
-            Ok(devices_battery)
+            /// ## Stream of `OnBattery` transitions, e.g. when the charger is unplugged
+            pub async fn on_battery_changes(&self) -> Result<EventStream<bool>> {
+                let proxy = UPowerProxy::new(self.system().await?).await?;
+                Ok(proxy
+                    .receive_OnBattery_changed()
+                    .await
+                    .then(|change| async move { Ok(change.get().await?) })
+                    .boxed())
+            }
+            /// ## Stream of devices plugged and unplugged
+            pub async fn device_events(&self) -> Result<EventStream<DeviceEvent>> {
+                let proxy = self.upower_proxy().await?;
+                let added = proxy
+                    .receive_DeviceAdded()
+                    .await?
+                    .map(|signal| Ok(DeviceEvent::Added(signal.args()?.device.to_string())));
+                let removed = proxy
+                    .receive_DeviceRemoved()
+                    .await?
+                    .map(|signal| Ok(DeviceEvent::Removed(signal.args()?.device.to_string())));
+                Ok(stream::select(added, removed).boxed())
+            }
+            /// ## Stream of snapshots of a device, one for each change of its properties
+            pub async fn device_changes(&self, path: &str) -> Result<EventStream<BatteryInfo>> {
+                property_changes(self.system().await?, path)
+                    .await?
+                    .ok_or_else(|| {
+                        Error::ServiceUnavailable(format!("No UPower device at {}", path))
+                    })
+            }
+        }
+
+        pub async fn get_current_device_battery() -> Result<BatteryInfo> {
+            GnomeClient::shared().current_device_battery().await
+        }
+        pub async fn get_devices_battery() -> Result<Vec<BatteryInfo>> {
+            GnomeClient::shared().devices_battery().await
+        }
+        pub async fn get_device_info(path: &str) -> Result<BatteryInfo> {
+            GnomeClient::shared().device_info(path).await
+        }
+        pub async fn get_history(
+            path: &str,
+            kind: HistoryKind,
+            timespan: Duration,
+            resolution: u32,
+        ) -> Result<Vec<HistoryPoint>> {
+            GnomeClient::shared()
+                .battery_history(path, kind, timespan, resolution)
+                .await
+        }
+        pub async fn get_statistics(
+            path: &str,
+            kind: StatisticsKind,
+        ) -> Result<Vec<StatisticsPoint>> {
+            GnomeClient::shared().battery_statistics(path, kind).await
+        }
+        pub async fn get_charge_limit(path: &str) -> Result<ChargeLimit> {
+            GnomeClient::shared().charge_limit(path).await
+        }
+        pub async fn set_charge_limit_enabled(path: &str, enabled: bool) -> Result<()> {
+            GnomeClient::shared()
+                .set_charge_limit_enabled(path, enabled)
+                .await
+        }
+        pub async fn low_battery_events(
+            thresholds: LowBatteryThresholds,
+        ) -> Result<EventStream<LowBatteryEvent>> {
+            GnomeClient::shared().low_battery_events(thresholds).await
+        }
+        pub async fn on_battery() -> Result<bool> {
+            GnomeClient::shared().on_battery().await
+        }
+        pub async fn lid_is_closed() -> Result<bool> {
+            GnomeClient::shared().lid_is_closed().await
+        }
+        pub async fn lid_is_present() -> Result<bool> {
+            GnomeClient::shared().lid_is_present().await
+        }
+        pub async fn on_battery_changes() -> Result<EventStream<bool>> {
+            GnomeClient::shared().on_battery_changes().await
+        }
+        pub async fn device_events() -> Result<EventStream<DeviceEvent>> {
+            GnomeClient::shared().device_events().await
+        }
+        pub async fn device_changes(path: &str) -> Result<EventStream<BatteryInfo>> {
+            GnomeClient::shared().device_changes(path).await
         }
     }
 
     pub mod extensions {
-        use zbus::Connection;
+        use futures_util::StreamExt;
 
-        use crate::handlers::easy_gnome::ExtensionsProxy;
+        use crate::dconf::Watch;
+        use crate::{EventStream, GnomeClient, Result};
 
-        use super::ListExtension;
+        use super::{ListExtension, ListExtensionState};
 
-        pub fn set_extensions_active(active: bool) {
-            crate::dconf::set(
-                "org.gnome.shell",
-                "disable-user-extensions",
-                active.to_string().as_str(),
-            )
-            .unwrap();
+        impl GnomeClient {
+            pub async fn extensions(&self) -> Result<Vec<ListExtension>> {
+                self.extensions_proxy().await?.list_extensions().await
+            }
+            pub async fn disable_extension(&self, uuid: &str) -> Result<bool> {
+                Ok(self
+                    .extensions_proxy()
+                    .await?
+                    .DisableExtension(uuid.to_string())
+                    .await?)
+            }
+            pub async fn enable_extension(&self, uuid: &str) -> Result<bool> {
+                Ok(self
+                    .extensions_proxy()
+                    .await?
+                    .EnableExtension(uuid.to_string())
+                    .await?)
+            }
+            pub async fn uninstall_extension(&self, uuid: &str) -> Result<bool> {
+                Ok(self
+                    .extensions_proxy()
+                    .await?
+                    .UninstallExtension(uuid.to_string())
+                    .await?)
+            }
+            pub async fn open_extension_preferences(&self, uuid: &str) -> Result<()> {
+                self.extensions_proxy()
+                    .await?
+                    .launch_extension_prefs(uuid)
+                    .await
+            }
+            /// ## Stream of `(uuid, extension)` each time an extension is enabled, disabled,
+            /// fails, is installed or uninstalled
+            pub async fn extension_state_changes(
+                &self,
+            ) -> Result<EventStream<(String, ListExtension)>> {
+                let changes = self
+                    .extensions_proxy()
+                    .await?
+                    .receive_ExtensionStateChanged()
+                    .await?;
+                Ok(changes
+                    .map(|signal| {
+                        let args = signal.args()?;
+                        Ok((
+                            args.uuid.clone(),
+                            ListExtension::from_info(&args.uuid, &args.state),
+                        ))
+                    })
+                    .boxed())
+            }
+            /// ## Stream of `(uuid, state, error)` from the older `ExtensionStatusChanged` signal
+            /// The error is `None` when empty.
+            pub async fn extension_status_changes(
+                &self,
+            ) -> Result<EventStream<(String, ListExtensionState, Option<String>)>> {
+                let changes = self
+                    .extensions_proxy()
+                    .await?
+                    .receive_ExtensionStatusChanged()
+                    .await?;
+                Ok(changes
+                    .map(|signal| {
+                        let args = signal.args()?;
+                        let error = Some(args.error.clone()).filter(|error| !error.is_empty());
+                        Ok((
+                            args.uuid.clone(),
+                            ListExtensionState::from(f64::from(args.state)),
+                            error,
+                        ))
+                    })
+                    .boxed())
+            }
+        }
+
+        pub fn set_extensions_active(active: bool) -> Result<()> {
+            crate::dconf::set("org.gnome.shell", "disable-user-extensions", active)
         }
-        pub fn get_extensions_active() -> bool {
-            let value = crate::dconf::get("org.gnome.shell", "disable-user-extensions").unwrap();
-            value.parse::<bool>().unwrap()
+        pub fn get_extensions_active() -> Result<bool> {
+            crate::dconf::get("org.gnome.shell", "disable-user-extensions")
         }
-        pub fn reset_extensions_active() {
-            crate::dconf::reset("org.gnome.shell", "disable-user-extensions").unwrap();
+        pub fn reset_extensions_active() -> Result<()> {
+            crate::dconf::reset("org.gnome.shell", "disable-user-extensions")
         }
-        pub async fn get_extensions() -> Vec<ListExtension> {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ExtensionsProxy::new(&connection).await.unwrap();
-            proxy.list_extensions().await
+        pub fn watch_extensions_active() -> Result<Watch<bool>> {
+            crate::dconf::watch("org.gnome.shell", "disable-user-extensions")
         }
-        pub async fn disable_extension(uuid: &str) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ExtensionsProxy::new(&connection).await.unwrap();
-            proxy.DisableExtension(uuid.to_string()).await.unwrap();
+        pub async fn get_extensions() -> Result<Vec<ListExtension>> {
+            GnomeClient::shared().extensions().await
         }
-        pub async fn enable_extension(uuid: &str) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ExtensionsProxy::new(&connection).await.unwrap();
-            proxy.EnableExtension(uuid.to_string()).await.unwrap();
+        pub async fn disable_extension(uuid: &str) -> Result<bool> {
+            GnomeClient::shared().disable_extension(uuid).await
         }
-        pub async fn uninstall_extension(uuid: &str) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ExtensionsProxy::new(&connection).await.unwrap();
-            proxy.UninstallExtension(uuid.to_string()).await.unwrap();
+        pub async fn enable_extension(uuid: &str) -> Result<bool> {
+            GnomeClient::shared().enable_extension(uuid).await
         }
-        pub async fn open_extension_preferences(uuid: &str) {
-            let connection = Connection::session().await.unwrap();
-            let proxy = ExtensionsProxy::new(&connection).await.unwrap();
-            proxy.launch_extension_prefs(uuid).await.unwrap();
+        pub async fn uninstall_extension(uuid: &str) -> Result<bool> {
+            GnomeClient::shared().uninstall_extension(uuid).await
+        }
+        pub async fn open_extension_preferences(uuid: &str) -> Result<()> {
+            GnomeClient::shared().open_extension_preferences(uuid).await
+        }
+        pub async fn state_changes() -> Result<EventStream<(String, ListExtension)>> {
+            GnomeClient::shared().extension_state_changes().await
+        }
+        pub async fn status_changes(
+        ) -> Result<EventStream<(String, ListExtensionState, Option<String>)>> {
+            GnomeClient::shared().extension_status_changes().await
         }
     }
 
     pub mod interface {
-        pub fn set_show_battery_percentage(show: bool) -> Result<(), String> {
+        use crate::Result;
+
+        pub fn set_show_battery_percentage(show: bool) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.desktop.interface",
                 "show-battery-percentage",
-                show.to_string().as_str(),
+                show,
             )
         }
-        pub fn get_show_battery_percentage() -> Result<bool, String> {
-            let value =
-                crate::dconf::get("org.gnome.desktop.interface", "show-battery-percentage")?;
-            Ok(value.parse::<bool>().unwrap())
+        pub fn get_show_battery_percentage() -> Result<bool> {
+            crate::dconf::get("org.gnome.desktop.interface", "show-battery-percentage")
         }
-        pub fn reset_show_battery_percentage() -> Result<(), String> {
+        pub fn reset_show_battery_percentage() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.interface", "show-battery-percentage")
         }
-        pub fn set_locate_pointer(enabled: bool) -> Result<(), String> {
-            crate::dconf::set(
-                "org.gnome.desktop.interface",
-                "locate-pointer",
-                enabled.to_string().as_str(),
-            )
+        pub fn set_locate_pointer(enabled: bool) -> Result<()> {
+            crate::dconf::set("org.gnome.desktop.interface", "locate-pointer", enabled)
         }
-        pub fn get_locate_pointer() -> Result<bool, String> {
-            let value = crate::dconf::get("org.gnome.desktop.interface", "locate-pointer")?;
-            Ok(value.parse::<bool>().unwrap())
+        pub fn get_locate_pointer() -> Result<bool> {
+            crate::dconf::get("org.gnome.desktop.interface", "locate-pointer")
         }
-        pub fn reset_locate_pointer() -> Result<(), String> {
+        pub fn reset_locate_pointer() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.interface", "locate-pointer")
         }
-        pub fn set_cursor_size(size: u32) -> Result<(), String> {
-            crate::dconf::set(
-                "org.gnome.desktop.interface",
-                "cursor-size",
-                size.to_string().as_str(),
-            )
+        pub fn set_cursor_size(size: u32) -> Result<()> {
+            // The schema stores the cursor size as a signed integer
+            let size = i32::try_from(size).map_err(|_| {
+                crate::Error::InvalidValue(format!("Cursor size {} is too big", size))
+            })?;
+            crate::dconf::set("org.gnome.desktop.interface", "cursor-size", size)
         }
-        pub fn get_cursor_size() -> Result<u32, String> {
-            let value = crate::dconf::get("org.gnome.desktop.interface", "cursor-size")?;
-            Ok(value.parse::<u32>().unwrap())
+        pub fn get_cursor_size() -> Result<u32> {
+            let size: i32 = crate::dconf::get("org.gnome.desktop.interface", "cursor-size")?;
+            u32::try_from(size)
+                .map_err(|_| crate::Error::Parse(format!("Negative cursor size {}", size)))
         }
-        pub fn reset_cursor_size() -> Result<(), String> {
+        pub fn reset_cursor_size() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.interface", "cursor-size")
         }
     }
 
     pub mod peripherals {
-        pub fn set_keyboard_press_delay(delay: u32) -> Result<(), String> {
-            crate::dconf::set(
-                "org.gnome.desktop.peripherals.keyboard",
-                "delay",
-                String::from(delay.to_string()).as_str(),
-            )
+        use crate::Result;
+
+        pub fn set_keyboard_press_delay(delay: u32) -> Result<()> {
+            crate::dconf::set("org.gnome.desktop.peripherals.keyboard", "delay", delay)
         }
-        pub fn get_keyboard_press_delay() -> Result<u32, String> {
-            let value: String =
-                crate::dconf::get("org.gnome.desktop.peripherals.keyboard", "delay")?;
-            Ok(value.parse::<u32>().unwrap())
+        pub fn get_keyboard_press_delay() -> Result<u32> {
+            crate::dconf::get("org.gnome.desktop.peripherals.keyboard", "delay")
         }
-        pub fn reset_keyboard_press_delay() -> Result<(), String> {
+        pub fn reset_keyboard_press_delay() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.peripherals.keyboard", "delay")
         }
-        pub fn set_keyboard_repeat_interval(interval: u32) -> Result<(), String> {
+        pub fn set_keyboard_repeat_interval(interval: u32) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.desktop.peripherals.keyboard",
                 "repeat-interval",
-                String::from(interval.to_string()).as_str(),
+                interval,
             )
         }
-        pub fn get_keyboard_repeat_interval() -> Result<u32, String> {
-            let value =
-                crate::dconf::get("org.gnome.desktop.peripherals.keyboard", "repeat-interval")?;
-            Ok(value.parse::<u32>().unwrap())
+        pub fn get_keyboard_repeat_interval() -> Result<u32> {
+            crate::dconf::get("org.gnome.desktop.peripherals.keyboard", "repeat-interval")
         }
-        pub fn reset_keyboard_repeat_interval() -> Result<(), String> {
+        pub fn reset_keyboard_repeat_interval() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.peripherals.keyboard", "repeat-interval")
         }
-        pub fn set_mouse_natural_scroll(enabled: bool) -> Result<(), String> {
+        pub fn set_mouse_natural_scroll(enabled: bool) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.desktop.peripherals.mouse",
                 "natural-scroll",
-                String::from(enabled.to_string()).as_str(),
+                enabled,
             )
         }
-        pub fn get_mouse_natural_scroll() -> Result<bool, String> {
-            let value = crate::dconf::get("org.gnome.desktop.peripherals.mouse", "natural-scroll")?;
-            Ok(value.parse::<bool>().unwrap())
+        pub fn get_mouse_natural_scroll() -> Result<bool> {
+            crate::dconf::get("org.gnome.desktop.peripherals.mouse", "natural-scroll")
         }
-        pub fn reset_mouse_natural_scroll() -> Result<(), String> {
+        pub fn reset_mouse_natural_scroll() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.peripherals.mouse", "natural-scroll")
         }
-        pub fn set_touchpad_tap_to_click(enabled: bool) -> Result<(), String> {
+        pub fn set_touchpad_tap_to_click(enabled: bool) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.desktop.peripherals.touchpad",
                 "tap-to-click",
-                String::from(enabled.to_string()).as_str(),
+                enabled,
             )
         }
-        pub fn get_touchpad_tap_to_click() -> Result<bool, String> {
-            let value =
-                crate::dconf::get("org.gnome.desktop.peripherals.touchpad", "tap-to-click")?;
-            Ok(value.parse::<bool>().unwrap())
+        pub fn get_touchpad_tap_to_click() -> Result<bool> {
+            crate::dconf::get("org.gnome.desktop.peripherals.touchpad", "tap-to-click")
         }
-        pub fn reset_touchpad_tap_to_click() -> Result<(), String> {
+        pub fn reset_touchpad_tap_to_click() -> Result<()> {
             crate::dconf::reset("org.gnome.desktop.peripherals.touchpad", "tap-to-click")
         }
-        pub fn set_two_finger_scroll(enabled: bool) -> Result<(), String> {
+        pub fn set_two_finger_scroll(enabled: bool) -> Result<()> {
             crate::dconf::set(
                 "org.gnome.desktop.peripherals.touchpad",
                 "two-finger-scrolling-enabled",
-                String::from(enabled.to_string()).as_str(),
+                enabled,
             )
         }
-        pub fn get_two_finger_scroll() -> Result<bool, String> {
-            let value = crate::dconf::get(
+        pub fn get_two_finger_scroll() -> Result<bool> {
+            crate::dconf::get(
                 "org.gnome.desktop.peripherals.touchpad",
                 "two-finger-scrolling-enabled",
-            )?;
-            Ok(value.parse::<bool>().unwrap())
+            )
         }
-        pub fn reset_two_finger_scroll() -> Result<(), String> {
+        pub fn reset_two_finger_scroll() -> Result<()> {
             crate::dconf::reset(
                 "org.gnome.desktop.peripherals.touchpad",
                 "two-finger-scrolling-enabled",
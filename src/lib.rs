@@ -1,5 +1,15 @@
 /* #![feature(ascii_char)] */
-mod dconf;
+mod client;
+pub mod dconf;
+mod error;
 pub mod handlers;
+pub mod schemas;
+#[cfg(test)]
+mod test_support;
 #[cfg(test)]
 pub mod tests;
+pub use client::GnomeClient;
+pub use error::{Error, Result};
+
+/// ## Stream of D-Bus changes returned by the `*_changes` and `*_events` functions
+pub type EventStream<T> = std::pin::Pin<Box<dyn futures_util::Stream<Item = Result<T>> + Send>>;
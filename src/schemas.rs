@@ -0,0 +1,144 @@
+use gio::glib::Variant;
+use gio::prelude::*;
+use gio::SettingsSchemaKey;
+
+use crate::dconf::{lookup, schema_source, settings};
+use crate::{Error, Result};
+
+/// ## Installed schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaInfo {
+    pub id: String,
+    /// `None` for relocatable schemas, they need a path given by the caller (`SCHEMA:PATH`)
+    pub path: Option<String>,
+    pub relocatable: bool,
+}
+
+/// ## Values accepted by a key
+/// Mirrors the `(sv)` tuple returned by `g_settings_schema_key_get_range`.
+#[derive(Debug, Clone)]
+pub enum KeyRange {
+    /// Any value of the key type
+    Type,
+    /// One of the listed nicks
+    Enum(Vec<String>),
+    /// Any combination of the listed nicks
+    Flags(Vec<String>),
+    /// A number between min and max (both included)
+    Range { min: Variant, max: Variant },
+}
+
+/// ## Description of a key
+#[derive(Debug, Clone)]
+pub struct KeyInfo {
+    pub schema: String,
+    pub name: String,
+    /// GVariant type string, e.g. `b`, `u`, `as`, `a(ss)`
+    pub value_type: String,
+    pub default_value: Variant,
+    pub range: KeyRange,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    /// `false` when the key is locked down by a system dconf profile.
+    /// `None` for relocatable schemas opened without a path.
+    pub writable: Option<bool>,
+}
+
+/// ## List installed schemas, relocatable ones included
+pub fn list_schemas() -> Result<Vec<SchemaInfo>> {
+    let source = schema_source()?;
+    let (non_relocatable, relocatable) = source.list_schemas(true);
+    let mut schemas: Vec<SchemaInfo> = Vec::new();
+    for id in non_relocatable {
+        let path = source
+            .lookup(&id, true)
+            .and_then(|schema| schema.path())
+            .map(|path| path.to_string());
+        schemas.push(SchemaInfo {
+            id: id.to_string(),
+            path,
+            relocatable: false,
+        });
+    }
+    for id in relocatable {
+        schemas.push(SchemaInfo {
+            id: id.to_string(),
+            path: None,
+            relocatable: true,
+        });
+    }
+    schemas.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(schemas)
+}
+
+/// ## List the keys of a schema
+pub fn list_keys(schema: &str) -> Result<Vec<String>> {
+    let (settings_schema, _) = lookup(schema)?;
+    let mut keys: Vec<String> = settings_schema
+        .list_keys()
+        .iter()
+        .map(|key| key.to_string())
+        .collect();
+    keys.sort();
+    Ok(keys)
+}
+
+/// ## Describe a key: type, default value, range, summary and writability
+pub fn key_info(schema: &str, key: &str) -> Result<KeyInfo> {
+    let (settings_schema, path) = lookup(schema)?;
+    if !settings_schema.has_key(key) {
+        return Err(Error::InvalidValue(format!(
+            "No such key \"{}\" in schema \"{}\"",
+            key, schema
+        )));
+    }
+    let writable = match settings(&settings_schema, schema, path) {
+        Ok(settings) => Some(settings.is_writable(key)),
+        Err(_) => None,
+    };
+    let schema_key = settings_schema.key(key);
+    Ok(KeyInfo {
+        schema: schema.to_string(),
+        name: key.to_string(),
+        value_type: schema_key.value_type().as_str().to_string(),
+        default_value: schema_key.default_value(),
+        range: key_range(&schema_key)?,
+        summary: schema_key.summary().map(|summary| summary.to_string()),
+        description: schema_key
+            .description()
+            .map(|description| description.to_string()),
+        writable,
+    })
+}
+
+/// ## Describe every key of a schema
+pub fn keys(schema: &str) -> Result<Vec<KeyInfo>> {
+    list_keys(schema)?
+        .iter()
+        .map(|key| key_info(schema, key))
+        .collect()
+}
+
+fn key_range(schema_key: &SettingsSchemaKey) -> Result<KeyRange> {
+    let range = schema_key.range();
+    let kind = range.child_value(0);
+    let values = range
+        .child_value(1)
+        .as_variant()
+        .ok_or_else(|| Error::Parse(format!("Invalid range {}", range.print(true))))?;
+    let nicks = || {
+        values
+            .get::<Vec<String>>()
+            .ok_or_else(|| Error::Parse(format!("Invalid choices {}", values.print(true))))
+    };
+    match kind.str() {
+        Some("type") => Ok(KeyRange::Type),
+        Some("enum") => Ok(KeyRange::Enum(nicks()?)),
+        Some("flags") => Ok(KeyRange::Flags(nicks()?)),
+        Some("range") => Ok(KeyRange::Range {
+            min: values.child_value(0),
+            max: values.child_value(1),
+        }),
+        _ => Err(Error::Parse(format!("Unknown range {}", range.print(true)))),
+    }
+}
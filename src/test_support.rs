@@ -0,0 +1,1371 @@
+//! Private D-Bus daemon serving mock GNOME and freedesktop services, and
+//! in-memory GSettings with the schemas bundled in `tests/schemas`.
+//! Both the session and the system bus addresses point at the private daemon,
+//! so the handlers can be tested without a desktop and without touching the real machine.
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::net::UnixStream;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+
+use zbus::{dbus_interface, Connection, ConnectionBuilder, SignalContext};
+use zvariant::{ObjectPath, OwnedFd, OwnedObjectPath, OwnedValue, Value};
+
+use crate::dconf::{self, Backend};
+use crate::GnomeClient;
+
+type Calls = Arc<Mutex<Vec<String>>>;
+
+static BUS: OnceLock<Bus> = OnceLock::new();
+static EMPTY_BUS: OnceLock<EmptyBus> = OnceLock::new();
+static SETTINGS: OnceLock<Mutex<()>> = OnceLock::new();
+static MOCK_SERVICES: async_lock::Mutex<()> = async_lock::Mutex::new(());
+
+pub struct Bus {
+    pub address: String,
+    connection: Connection,
+    calls: Calls,
+    _daemon: Child,
+    // The daemon is killed when this pipe closes, i.e. when the test process exits
+    _daemon_stdin: ChildStdin,
+}
+
+/// ## Start the private bus and the mock services, once per test process
+/// The guard serializes the tests using the mock services,
+/// they all read and change the same extensions, devices and profiles.
+pub async fn bus() -> BusGuard {
+    let guard = MOCK_SERVICES.lock().await;
+    BusGuard {
+        bus: shared_bus(),
+        _guard: guard,
+    }
+}
+
+/// The main bus without taking the mock services, for the helpers of tests
+/// that only run their own services
+fn shared_bus() -> &'static Bus {
+    BUS.get_or_init(start)
+}
+
+pub struct BusGuard {
+    bus: &'static Bus,
+    _guard: async_lock::MutexGuard<'static, ()>,
+}
+
+impl std::ops::Deref for BusGuard {
+    type Target = Bus;
+
+    fn deref(&self) -> &Bus {
+        self.bus
+    }
+}
+
+/// ## New client on the main bus, with its own connections and proxies
+pub async fn client() -> GnomeClient {
+    let address = &shared_bus().address;
+    client_with_buses(address, address).await
+}
+
+/// ## Use in-memory settings with the bundled schemas
+/// The guard serializes the tests reading and writing settings,
+/// they all share the same memory backend.
+pub fn settings() -> MutexGuard<'static, ()> {
+    SETTINGS
+        .get_or_init(|| {
+            let schema_dir =
+                std::env::temp_dir().join(format!("gnome-dbus-api-schemas-{}", std::process::id()));
+            std::fs::create_dir_all(&schema_dir).unwrap();
+            let status = Command::new("glib-compile-schemas")
+                .arg("--strict")
+                .arg("--targetdir")
+                .arg(&schema_dir)
+                .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/schemas"))
+                .status()
+                .expect("glib-compile-schemas is needed to run the tests");
+            assert!(status.success(), "Unable to compile the test schemas");
+            dconf::configure(Backend::Memory, Some(schema_dir));
+            Mutex::new(())
+        })
+        .lock()
+        .unwrap_or_else(|error| error.into_inner())
+}
+
+impl Bus {
+    /// ## Connection owning the mock services
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+    /// ## Whether a mock method was called, e.g. `login1.Suspend(true)`
+    pub fn was_called(&self, call: &str) -> bool {
+        self.calls.lock().unwrap().iter().any(|c| c == call)
+    }
+    /// ## Plug or unplug the charger of the mock UPower
+    pub async fn set_on_battery(&self, on_battery: bool) {
+        let upower = self
+            .connection
+            .object_server()
+            .interface::<_, UPower>("/org/freedesktop/UPower")
+            .await
+            .unwrap();
+        upower.get_mut().await.on_battery = on_battery;
+        upower
+            .get()
+            .await
+            .on_battery_changed(upower.signal_context())
+            .await
+            .unwrap();
+    }
+    /// ## Make the mock `PickColor` answer as if the user pressed Escape
+    pub async fn set_pick_cancelled(&self, cancelled: bool) {
+        let screenshot = self
+            .connection
+            .object_server()
+            .interface::<_, ShellScreenshot>("/org/gnome/Shell/Screenshot")
+            .await
+            .unwrap();
+        screenshot.get_mut().await.cancel_pick = cancelled;
+    }
+    /// ## Emit `DeviceAdded` or `DeviceRemoved` for a device name, e.g. `mouse_dev_01`
+    pub async fn emit_device(&self, name: &str, added: bool) {
+        let ctxt = SignalContext::new(&self.connection, "/org/freedesktop/UPower").unwrap();
+        let path =
+            ObjectPath::try_from(format!("/org/freedesktop/UPower/devices/{}", name)).unwrap();
+        if added {
+            UPower::device_added(&ctxt, path).await.unwrap();
+        } else {
+            UPower::device_removed(&ctxt, path).await.unwrap();
+        }
+    }
+    /// ## Serve a new device, e.g. `keyboard_dev_00`, and emit `DeviceAdded`
+    /// It is a copy of the mock mouse with another kind and percentage.
+    pub async fn plug_device(&self, name: &'static str, kind: u32, percentage: f64) {
+        let mouse = upower_devices()
+            .into_iter()
+            .find(|device| device.name == "mouse_dev_00")
+            .unwrap();
+        let device = UPowerDevice {
+            name,
+            kind,
+            percentage,
+            ..mouse
+        };
+        self.connection
+            .object_server()
+            .at(device.path(), device)
+            .await
+            .unwrap();
+        self.emit_device(name, true).await;
+    }
+    /// ## Stop serving a device added with [`Bus::plug_device`] and emit `DeviceRemoved`
+    pub async fn unplug_device(&self, name: &str) {
+        self.connection
+            .object_server()
+            .remove::<UPowerDevice, _>(format!("/org/freedesktop/UPower/devices/{}", name))
+            .await
+            .unwrap();
+        self.emit_device(name, false).await;
+    }
+    /// ## Change the `WarningLevel` of a mock device, emitting `PropertiesChanged`
+    pub async fn set_device_warning_level(&self, name: &str, level: u32) {
+        let device = self
+            .connection
+            .object_server()
+            .interface::<_, UPowerDevice>(format!("/org/freedesktop/UPower/devices/{}", name))
+            .await
+            .unwrap();
+        device.get_mut().await.warning_level = level;
+        device
+            .get()
+            .await
+            .warning_level_changed(device.signal_context())
+            .await
+            .unwrap();
+    }
+    /// ## Change the percentage of a mock device, emitting `PropertiesChanged`
+    pub async fn set_device_percentage(&self, name: &str, percentage: f64) {
+        let device = self
+            .connection
+            .object_server()
+            .interface::<_, UPowerDevice>(format!("/org/freedesktop/UPower/devices/{}", name))
+            .await
+            .unwrap();
+        device.get_mut().await.percentage = percentage;
+        device
+            .get()
+            .await
+            .percentage_changed(device.signal_context())
+            .await
+            .unwrap();
+    }
+}
+
+/// ## Second private bus where no service runs
+/// Used to test fallbacks and missing services.
+pub fn empty_bus() -> &'static EmptyBus {
+    EMPTY_BUS.get_or_init(|| {
+        let (address, daemon, daemon_stdin) = spawn_daemon();
+        EmptyBus {
+            address,
+            _daemon: daemon,
+            _daemon_stdin: daemon_stdin,
+        }
+    })
+}
+
+pub struct EmptyBus {
+    pub address: String,
+    _daemon: Child,
+    _daemon_stdin: ChildStdin,
+}
+
+/// ## Client whose session bus is empty and whose system bus has the mock services
+pub async fn client_without_session_services() -> GnomeClient {
+    let address = &shared_bus().address;
+    let session = ConnectionBuilder::address(empty_bus().address.as_str())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let system = ConnectionBuilder::address(address.as_str())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    GnomeClient::with_connections(session, system)
+}
+
+/// ## New private bus, stopped when dropped
+pub fn new_bus() -> EmptyBus {
+    let (address, daemon, daemon_stdin) = spawn_daemon();
+    EmptyBus {
+        address,
+        _daemon: daemon,
+        _daemon_stdin: daemon_stdin,
+    }
+}
+
+/// ## Serve only the legacy `net.hadess.PowerProfiles` name on a bus
+/// With `tuned`, also own the name of the tuned daemon, like tuned-ppd does.
+pub async fn serve_legacy_power_profiles(address: &str, tuned: bool) -> Connection {
+    let mut builder = ConnectionBuilder::address(address)
+        .unwrap()
+        .name("net.hadess.PowerProfiles")
+        .unwrap()
+        .serve_at(
+            "/net/hadess/PowerProfiles",
+            LegacyPowerProfiles {
+                active_profile: "power-saver".to_string(),
+            },
+        )
+        .unwrap();
+    if tuned {
+        builder = builder.name("com.redhat.tuned").unwrap();
+    }
+    builder.build().await.unwrap()
+}
+
+/// ## Serve only a screencast service on a bus, it stops when the connection is dropped
+pub async fn serve_screencast(address: &str) -> Connection {
+    ConnectionBuilder::address(address)
+        .unwrap()
+        .name("org.gnome.Shell.Screencast")
+        .unwrap()
+        .serve_at(
+            "/org/gnome/Shell/Screencast",
+            ShellScreencast {
+                calls: Calls::default(),
+                recording: false,
+            },
+        )
+        .unwrap()
+        .build()
+        .await
+        .unwrap()
+}
+
+/// ## Serve only UPower keyboard backlight on a bus, `max` 0 meaning no backlight
+pub async fn serve_kbd_backlight(address: &str, max: i32) -> Connection {
+    ConnectionBuilder::address(address)
+        .unwrap()
+        .name("org.freedesktop.UPower")
+        .unwrap()
+        .serve_at(
+            "/org/freedesktop/UPower/KbdBacklight",
+            KbdBacklight { brightness: 0, max },
+        )
+        .unwrap()
+        .build()
+        .await
+        .unwrap()
+}
+
+/// ## Serve only the keyboard interface of gnome-settings-daemon on a bus
+pub async fn serve_settings_daemon_keyboard(address: &str) -> Connection {
+    ConnectionBuilder::address(address)
+        .unwrap()
+        .name("org.gnome.SettingsDaemon.Power")
+        .unwrap()
+        .serve_at(
+            "/org/gnome/SettingsDaemon/Power",
+            SettingsDaemonKeyboard {
+                brightness: 40,
+                before_toggle: 40,
+            },
+        )
+        .unwrap()
+        .build()
+        .await
+        .unwrap()
+}
+
+/// ## Client using two other buses
+pub async fn client_with_buses(session: &str, system: &str) -> GnomeClient {
+    let session = ConnectionBuilder::address(session)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let system = ConnectionBuilder::address(system)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    GnomeClient::with_connections(session, system)
+}
+
+/// ## Client using another bus as session bus and the main bus as system bus
+pub async fn client_with_session_bus(address: &str) -> GnomeClient {
+    let session = ConnectionBuilder::address(address)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let system = ConnectionBuilder::address(shared_bus().address.as_str())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    GnomeClient::with_connections(session, system)
+}
+
+/// ## Client using the main bus as session bus and another bus as system bus
+pub async fn client_with_system_bus(address: &str) -> GnomeClient {
+    let session = ConnectionBuilder::address(shared_bus().address.as_str())
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    let system = ConnectionBuilder::address(address)
+        .unwrap()
+        .build()
+        .await
+        .unwrap();
+    GnomeClient::with_connections(session, system)
+}
+
+fn spawn_daemon() -> (String, Child, ChildStdin) {
+    let mut daemon = Command::new("sh")
+        .arg("-c")
+        .arg(
+            "dbus-daemon --session --nofork --print-address=1 & pid=$!; cat > /dev/null; kill $pid",
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("dbus-daemon is needed to run the tests");
+    let daemon_stdin = daemon.stdin.take().unwrap();
+    let mut address = String::new();
+    BufReader::new(daemon.stdout.take().unwrap())
+        .read_line(&mut address)
+        .expect("Unable to read the dbus-daemon address");
+    (address.trim().to_string(), daemon, daemon_stdin)
+}
+
+fn start() -> Bus {
+    let (address, daemon, daemon_stdin) = spawn_daemon();
+
+    let calls = Calls::default();
+    let connection =
+        zbus::block_on(serve(&address, calls.clone())).expect("Unable to serve the mock services");
+    // The free functions use the private bus as both session and system bus,
+    // setting DBUS_*_BUS_ADDRESS would race with the test threads reading the environment
+    GnomeClient::set_shared(zbus::block_on(client_with_buses(&address, &address)));
+    Bus {
+        address,
+        connection,
+        calls,
+        _daemon: daemon,
+        _daemon_stdin: daemon_stdin,
+    }
+}
+
+async fn serve(address: &str, calls: Calls) -> zbus::Result<Connection> {
+    let mut builder = ConnectionBuilder::address(address)?
+        .name("org.freedesktop.login1")?
+        .name("org.freedesktop.UPower.PowerProfiles")?
+        .name("net.hadess.PowerProfiles")?
+        .name("org.gnome.Shell.Extensions")?
+        .name("org.gnome.Shell.Screenshot")?
+        .name("org.gnome.Shell.Screencast")?
+        .name("org.gnome.SettingsDaemon.Power")?
+        .name("org.freedesktop.UPower")?
+        .serve_at(
+            "/org/freedesktop/login1",
+            Login1 {
+                calls: calls.clone(),
+                inhibitors: Vec::new(),
+                scheduled_shutdown: (String::new(), 0),
+            },
+        )?
+        .serve_at(
+            "/net/hadess/PowerProfiles",
+            LegacyPowerProfiles {
+                active_profile: "performance".to_string(),
+            },
+        )?
+        .serve_at(
+            "/org/freedesktop/UPower/PowerProfiles",
+            PowerProfiles {
+                calls: calls.clone(),
+                active_profile: "balanced".to_string(),
+                next_cookie: 0,
+            },
+        )?
+        .serve_at(
+            "/org/gnome/Shell/Extensions",
+            ShellExtensions {
+                calls: calls.clone(),
+                extensions: HashMap::from([
+                    ("extension-list@tu.berry".to_string(), 1.0),
+                    ("ubuntu-appindicators@ubuntu.com".to_string(), 1.0),
+                    ("uninstall-me@example.com".to_string(), 2.0),
+                    ("system-broken@example.com".to_string(), 7.0),
+                ]),
+            },
+        )?
+        .serve_at(
+            "/org/gnome/Shell/Screenshot",
+            ShellScreenshot {
+                calls: calls.clone(),
+                cancel_pick: false,
+            },
+        )?
+        .serve_at(
+            "/org/gnome/Shell/Screencast",
+            ShellScreencast {
+                calls: calls.clone(),
+                recording: false,
+            },
+        )?
+        .serve_at(
+            "/org/gnome/SettingsDaemon/Power",
+            SettingsDaemonScreen { brightness: 50 },
+        )?
+        .serve_at(
+            "/org/gnome/SettingsDaemon/Power",
+            SettingsDaemonKeyboard {
+                brightness: 40,
+                before_toggle: 40,
+            },
+        )?
+        .serve_at("/org/freedesktop/UPower", UPower { on_battery: true })?
+        .serve_at(
+            "/org/freedesktop/UPower/KbdBacklight",
+            KbdBacklight {
+                brightness: 1,
+                max: 3,
+            },
+        )?;
+    for device in upower_devices() {
+        builder = builder.serve_at(device.path(), device)?;
+    }
+    builder.build().await
+}
+
+fn record(calls: &Calls, call: String) {
+    calls.lock().unwrap().push(call);
+}
+
+struct Login1 {
+    calls: Calls,
+    /// Our end of the pipe given to each inhibitor, with `what`, `who`, `why` and `mode`
+    inhibitors: Vec<(UnixStream, (String, String, String, String))>,
+    scheduled_shutdown: (String, u64),
+}
+
+/// Whether the other end of the pipe is still open
+fn is_open(stream: &UnixStream) -> bool {
+    let mut buffer = [0u8; 1];
+    stream.set_nonblocking(true).unwrap();
+    !matches!((&*stream).read(&mut buffer), Ok(0))
+}
+
+#[dbus_interface(name = "org.freedesktop.login1.Manager")]
+impl Login1 {
+    fn suspend(&self, interactive: bool) {
+        record(&self.calls, format!("login1.Suspend({})", interactive));
+    }
+    fn power_off(&self, interactive: bool) {
+        record(&self.calls, format!("login1.PowerOff({})", interactive));
+    }
+    fn reboot(&self, interactive: bool) {
+        record(&self.calls, format!("login1.Reboot({})", interactive));
+    }
+    fn hibernate(&self, interactive: bool) -> zbus::fdo::Result<()> {
+        Err(zbus::fdo::Error::NotSupported(format!(
+            "Hibernation is not configured (interactive: {})",
+            interactive
+        )))
+    }
+    fn hybrid_sleep(&self, interactive: bool) -> zbus::fdo::Result<()> {
+        if !interactive {
+            return Err(zbus::fdo::Error::InteractiveAuthorizationRequired(
+                "Interactive authentication required.".to_string(),
+            ));
+        }
+        record(&self.calls, format!("login1.HybridSleep({})", interactive));
+        Ok(())
+    }
+    fn suspend_then_hibernate(&self, interactive: bool) {
+        record(
+            &self.calls,
+            format!("login1.SuspendThenHibernate({})", interactive),
+        );
+    }
+    fn inhibit(
+        &mut self,
+        what: String,
+        who: String,
+        why: String,
+        mode: String,
+    ) -> zbus::fdo::Result<OwnedFd> {
+        let (ours, theirs) =
+            UnixStream::pair().map_err(|error| zbus::fdo::Error::IOError(error.to_string()))?;
+        self.inhibitors.push((ours, (what, who, why, mode)));
+        // The raw descriptor comes straight from `into_raw_fd`, nothing else owns it
+        Ok(unsafe { OwnedFd::from_raw_fd(theirs.into_raw_fd()) })
+    }
+    fn list_inhibitors(&mut self) -> Vec<(String, String, String, String, u32, u32)> {
+        self.inhibitors.retain(|(stream, _)| is_open(stream));
+        self.inhibitors
+            .iter()
+            .map(|(_, (what, who, why, mode))| {
+                (
+                    what.clone(),
+                    who.clone(),
+                    why.clone(),
+                    mode.clone(),
+                    1000,
+                    std::process::id(),
+                )
+            })
+            .collect()
+    }
+    fn schedule_shutdown(&mut self, kind: String, usec: u64) -> zbus::fdo::Result<()> {
+        let valid = ["poweroff", "reboot", "halt", "kexec"];
+        if !valid.contains(&kind.trim_start_matches("dry-")) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Unsupported shutdown type: {}",
+                kind
+            )));
+        }
+        self.scheduled_shutdown = (kind, usec);
+        Ok(())
+    }
+    fn cancel_scheduled_shutdown(&mut self) -> bool {
+        let cancelled = !self.scheduled_shutdown.0.is_empty();
+        self.scheduled_shutdown = (String::new(), 0);
+        cancelled
+    }
+    #[dbus_interface(property)]
+    fn scheduled_shutdown(&self) -> (String, u64) {
+        self.scheduled_shutdown.clone()
+    }
+    fn can_power_off(&self) -> String {
+        "yes".to_string()
+    }
+    fn can_reboot(&self) -> String {
+        "yes".to_string()
+    }
+    fn can_suspend(&self) -> String {
+        "yes".to_string()
+    }
+    fn can_hibernate(&self) -> String {
+        "na".to_string()
+    }
+    fn can_hybrid_sleep(&self) -> String {
+        "challenge".to_string()
+    }
+    fn can_suspend_then_hibernate(&self) -> String {
+        "no".to_string()
+    }
+}
+
+struct PowerProfiles {
+    calls: Calls,
+    active_profile: String,
+    next_cookie: u32,
+}
+
+fn profile_info(profile: &str, driver: &str) -> HashMap<String, OwnedValue> {
+    HashMap::from([
+        ("Profile".to_string(), Value::from(profile).into()),
+        ("Driver".to_string(), Value::from(driver).into()),
+        ("PlatformDriver".to_string(), Value::from(driver).into()),
+    ])
+}
+
+#[dbus_interface(name = "org.freedesktop.UPower.PowerProfiles")]
+impl PowerProfiles {
+    #[dbus_interface(property)]
+    fn active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+    #[dbus_interface(property)]
+    fn set_active_profile(&mut self, profile: String) {
+        self.active_profile = profile;
+    }
+    /// A machine without a performance mode, plus a profile from a future daemon
+    #[dbus_interface(property)]
+    fn profiles(&self) -> Vec<HashMap<String, OwnedValue>> {
+        vec![
+            profile_info("power-saver", "platform_profile"),
+            profile_info("balanced", "platform_profile"),
+            profile_info("quiet", "platform_profile"),
+        ]
+    }
+    #[dbus_interface(property)]
+    fn performance_degraded(&self) -> String {
+        "lap-detected".to_string()
+    }
+    fn hold_profile(&mut self, profile: String, reason: String, application_id: String) -> u32 {
+        self.next_cookie += 1;
+        record(
+            &self.calls,
+            format!(
+                "ppd.HoldProfile({}, {}, {}) = {}",
+                profile, reason, application_id, self.next_cookie
+            ),
+        );
+        self.next_cookie
+    }
+    fn release_profile(&self, cookie: u32) {
+        record(&self.calls, format!("ppd.ReleaseProfile({})", cookie));
+    }
+}
+
+/// Old power-profiles-daemon, only the active profile
+struct LegacyPowerProfiles {
+    active_profile: String,
+}
+
+#[dbus_interface(name = "net.hadess.PowerProfiles")]
+impl LegacyPowerProfiles {
+    #[dbus_interface(property)]
+    fn active_profile(&self) -> String {
+        self.active_profile.clone()
+    }
+    #[dbus_interface(property)]
+    fn set_active_profile(&mut self, profile: String) {
+        self.active_profile = profile;
+    }
+}
+
+struct ShellExtensions {
+    calls: Calls,
+    // uuid -> state
+    extensions: HashMap<String, f64>,
+}
+
+/// Full info like GNOME 45, except for `system-broken@example.com`
+/// which only has its uuid and a state unknown to the crate
+fn extension_info(uuid: &str, state: f64) -> HashMap<String, OwnedValue> {
+    if uuid == "system-broken@example.com" {
+        return HashMap::from([
+            ("uuid".to_string(), Value::from(uuid).into()),
+            ("state".to_string(), Value::from(state).into()),
+            ("type".to_string(), Value::from(1.0).into()),
+        ]);
+    }
+    HashMap::from([
+        ("uuid".to_string(), Value::from(uuid).into()),
+        ("name".to_string(), Value::from(uuid).into()),
+        (
+            "description".to_string(),
+            Value::from("Mock extension").into(),
+        ),
+        ("version".to_string(), Value::from(3.0).into()),
+        ("state".to_string(), Value::from(state).into()),
+        ("url".to_string(), Value::from("https://example.com").into()),
+        ("type".to_string(), Value::from(2.0).into()),
+        (
+            "path".to_string(),
+            Value::from(format!(
+                "/home/user/.local/share/gnome-shell/extensions/{}",
+                uuid
+            ))
+            .into(),
+        ),
+        ("error".to_string(), Value::from("").into()),
+        ("hasPrefs".to_string(), Value::from(true).into()),
+        ("hasUpdate".to_string(), Value::from(false).into()),
+        ("canChange".to_string(), Value::from(true).into()),
+        (
+            "shell-version".to_string(),
+            Value::from(vec!["45", "46"]).into(),
+        ),
+        (
+            "settings-schema".to_string(),
+            Value::from("org.gnome.shell.extensions.mock").into(),
+        ),
+        ("enabled".to_string(), Value::from(state == 1.0).into()),
+    ])
+}
+
+#[dbus_interface(name = "org.gnome.Shell.Extensions")]
+impl ShellExtensions {
+    fn list_extensions(&self) -> HashMap<String, HashMap<String, OwnedValue>> {
+        self.extensions
+            .iter()
+            .map(|(uuid, state)| (uuid.clone(), extension_info(uuid, *state)))
+            .collect()
+    }
+    fn launch_extension_prefs(&self, uuid: String) {
+        record(
+            &self.calls,
+            format!("extensions.LaunchExtensionPrefs({})", uuid),
+        );
+    }
+    async fn enable_extension(
+        &mut self,
+        uuid: String,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> bool {
+        self.set_state(&ctxt, uuid, 1.0).await
+    }
+    async fn disable_extension(
+        &mut self,
+        uuid: String,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> bool {
+        self.set_state(&ctxt, uuid, 2.0).await
+    }
+    async fn uninstall_extension(
+        &mut self,
+        uuid: String,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> bool {
+        let removed = self.extensions.remove(&uuid).is_some();
+        if removed {
+            ShellExtensions::emit_state(&ctxt, &uuid, 99.0).await;
+        }
+        removed
+    }
+    #[dbus_interface(signal)]
+    async fn extension_state_changed(
+        ctxt: &SignalContext<'_>,
+        uuid: &str,
+        state: HashMap<String, OwnedValue>,
+    ) -> zbus::Result<()>;
+    #[dbus_interface(signal)]
+    async fn extension_status_changed(
+        ctxt: &SignalContext<'_>,
+        uuid: &str,
+        state: i32,
+        error: &str,
+    ) -> zbus::Result<()>;
+}
+
+impl ShellExtensions {
+    async fn set_state(&mut self, ctxt: &SignalContext<'_>, uuid: String, state: f64) -> bool {
+        match self.extensions.get_mut(&uuid) {
+            Some(current) => {
+                *current = state;
+                ShellExtensions::emit_state(ctxt, &uuid, state).await;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Emit both signals, like GNOME Shell does
+    async fn emit_state(ctxt: &SignalContext<'_>, uuid: &str, state: f64) {
+        ShellExtensions::extension_state_changed(ctxt, uuid, extension_info(uuid, state))
+            .await
+            .unwrap();
+        ShellExtensions::extension_status_changed(ctxt, uuid, state as i32, "")
+            .await
+            .unwrap();
+    }
+}
+
+struct ShellScreenshot {
+    calls: Calls,
+    /// `PickColor` fails like GNOME Shell when the user presses Escape
+    cancel_pick: bool,
+}
+
+/// Write a plain red PNG, standing in for the captured pixels
+fn write_capture(width: i32, height: i32, filename: &str) -> (bool, String) {
+    let image =
+        image::RgbaImage::from_pixel(width as u32, height as u32, image::Rgba([255, 0, 0, 255]));
+    (image.save(filename).is_ok(), filename.to_string())
+}
+
+/// `G_IO_ERROR_CANCELLED`, as GNOME Shell reports a dismissed request
+#[derive(zbus::DBusError, Debug)]
+#[dbus_error(prefix = "org.gtk.GDBus.UnmappedGError.Quark._g_2dio_2derror_2dquark")]
+enum GioError {
+    #[dbus_error(zbus_error)]
+    ZBus(zbus::Error),
+    Code19(String),
+}
+
+#[dbus_interface(name = "org.gnome.Shell.Screenshot")]
+impl ShellScreenshot {
+    /// A file named `cancel.png` stands for the user pressing Escape
+    fn screenshot(
+        &self,
+        include_cursor: bool,
+        flash: bool,
+        filename: String,
+    ) -> Result<(bool, String), GioError> {
+        record(
+            &self.calls,
+            format!("screenshot.Screenshot({}, {})", include_cursor, flash),
+        );
+        if filename.ends_with("cancel.png") {
+            return Err(GioError::Code19("Operation was cancelled".to_string()));
+        }
+        Ok(write_capture(64, 48, &filename))
+    }
+    fn screenshot_window(
+        &self,
+        include_frame: bool,
+        include_cursor: bool,
+        flash: bool,
+        filename: String,
+    ) -> (bool, String) {
+        record(
+            &self.calls,
+            format!(
+                "screenshot.ScreenshotWindow({}, {}, {})",
+                include_frame, include_cursor, flash
+            ),
+        );
+        write_capture(32, 24, &filename)
+    }
+    fn screenshot_area(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        flash: bool,
+        filename: String,
+    ) -> (bool, String) {
+        record(
+            &self.calls,
+            format!("screenshot.ScreenshotArea({}, {}, {})", x, y, flash),
+        );
+        write_capture(width, height, &filename)
+    }
+    fn select_area(&self) -> (i32, i32, i32, i32) {
+        (10, 20, 30, 40)
+    }
+    fn flash_area(&self, x: i32, y: i32, width: i32, height: i32) {
+        record(
+            &self.calls,
+            format!("screenshot.FlashArea({}, {}, {}, {})", x, y, width, height),
+        );
+    }
+    fn pick_color(&self) -> Result<HashMap<String, OwnedValue>, GioError> {
+        if self.cancel_pick {
+            return Err(GioError::Code19("Operation was cancelled".to_string()));
+        }
+        Ok(HashMap::from([(
+            "color".to_string(),
+            Value::from((0.5f64, 0.25f64, 1.0f64)).into(),
+        )]))
+    }
+}
+
+struct ShellScreencast {
+    calls: Calls,
+    recording: bool,
+}
+
+impl ShellScreencast {
+    fn start(
+        &mut self,
+        file_template: &str,
+        options: &HashMap<String, OwnedValue>,
+    ) -> (bool, String) {
+        if self.recording {
+            return (false, String::new());
+        }
+        self.recording = true;
+        let mut keys: Vec<&str> = options.keys().map(|key| key.as_str()).collect();
+        keys.sort();
+        record(
+            &self.calls,
+            format!("screencast.Screencast({})", keys.join(", ")),
+        );
+        let filename = file_template
+            .replace("%d", "2024-01-01")
+            .replace("%t", "12-00-00");
+        (true, format!("/home/user/Videos/{}.webm", filename))
+    }
+}
+
+#[dbus_interface(name = "org.gnome.Shell.Screencast")]
+impl ShellScreencast {
+    fn screencast(
+        &mut self,
+        file_template: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> (bool, String) {
+        self.start(&file_template, &options)
+    }
+    fn screencast_area(
+        &mut self,
+        _x: i32,
+        _y: i32,
+        _width: i32,
+        _height: i32,
+        file_template: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> (bool, String) {
+        self.start(&file_template, &options)
+    }
+    fn stop_screencast(&mut self) -> bool {
+        std::mem::replace(&mut self.recording, false)
+    }
+}
+
+struct SettingsDaemonScreen {
+    brightness: i32,
+}
+
+#[dbus_interface(name = "org.gnome.SettingsDaemon.Power.Screen")]
+impl SettingsDaemonScreen {
+    #[dbus_interface(property)]
+    fn brightness(&self) -> i32 {
+        self.brightness
+    }
+    #[dbus_interface(property)]
+    fn set_brightness(&mut self, brightness: i32) {
+        self.brightness = brightness;
+    }
+    async fn step_up(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        self.brightness = (self.brightness + 10).min(100);
+        self.brightness_changed(&ctxt).await?;
+        Ok(())
+    }
+    async fn step_down(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        self.brightness = (self.brightness - 10).max(0);
+        self.brightness_changed(&ctxt).await?;
+        Ok(())
+    }
+}
+
+struct SettingsDaemonKeyboard {
+    brightness: i32,
+    before_toggle: i32,
+}
+
+impl SettingsDaemonKeyboard {
+    async fn update(
+        &mut self,
+        brightness: i32,
+        ctxt: &SignalContext<'_>,
+    ) -> zbus::fdo::Result<i32> {
+        self.brightness = brightness;
+        self.brightness_changed(ctxt).await?;
+        Self::brightness_signal(ctxt, brightness, "external").await?;
+        Ok(brightness)
+    }
+}
+
+#[dbus_interface(name = "org.gnome.SettingsDaemon.Power.Keyboard")]
+impl SettingsDaemonKeyboard {
+    #[dbus_interface(property)]
+    fn brightness(&self) -> i32 {
+        self.brightness
+    }
+    #[dbus_interface(property)]
+    fn set_brightness(&mut self, brightness: i32) {
+        self.brightness = brightness;
+    }
+    async fn step_up(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<i32> {
+        self.update((self.brightness + 10).min(100), &ctxt).await
+    }
+    async fn step_down(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<i32> {
+        self.update((self.brightness - 10).max(0), &ctxt).await
+    }
+    async fn toggle(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<i32> {
+        if self.brightness > 0 {
+            self.before_toggle = self.brightness;
+            self.update(0, &ctxt).await
+        } else {
+            self.update(self.before_toggle, &ctxt).await
+        }
+    }
+    #[dbus_interface(signal, name = "BrightnessChanged")]
+    async fn brightness_signal(
+        ctxt: &SignalContext<'_>,
+        brightness: i32,
+        source: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Keyboard with three backlight levels
+struct KbdBacklight {
+    brightness: i32,
+    max: i32,
+}
+
+#[dbus_interface(name = "org.freedesktop.UPower.KbdBacklight")]
+impl KbdBacklight {
+    fn get_max_brightness(&self) -> i32 {
+        self.max
+    }
+    fn get_brightness(&self) -> i32 {
+        self.brightness
+    }
+    async fn set_brightness(
+        &mut self,
+        value: i32,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if !(0..=self.max).contains(&value) {
+            return Err(zbus::fdo::Error::InvalidArgs(format!(
+                "Brightness {} out of range",
+                value
+            )));
+        }
+        self.brightness = value;
+        Self::brightness_changed(&ctxt, value).await?;
+        Ok(())
+    }
+    #[dbus_interface(signal)]
+    async fn brightness_changed(ctxt: &SignalContext<'_>, value: i32) -> zbus::Result<()>;
+}
+
+struct UPower {
+    on_battery: bool,
+}
+
+#[dbus_interface(name = "org.freedesktop.UPower")]
+impl UPower {
+    /// Also lists `headset_dev_gone`, which is not served: a device
+    /// disconnected between `EnumerateDevices` and the reads of its properties
+    fn enumerate_devices(&self) -> Vec<OwnedObjectPath> {
+        upower_devices()
+            .iter()
+            .filter(|device| device.name != "DisplayDevice")
+            .map(|device| device.path())
+            .chain(["/org/freedesktop/UPower/devices/headset_dev_gone".to_string()])
+            .map(|path| OwnedObjectPath::try_from(path).unwrap())
+            .collect()
+    }
+    fn get_display_device(&self) -> OwnedObjectPath {
+        OwnedObjectPath::try_from("/org/freedesktop/UPower/devices/DisplayDevice").unwrap()
+    }
+    #[dbus_interface(property)]
+    fn on_battery(&self) -> bool {
+        self.on_battery
+    }
+    #[dbus_interface(property)]
+    fn lid_is_closed(&self) -> bool {
+        false
+    }
+    #[dbus_interface(property)]
+    fn lid_is_present(&self) -> bool {
+        true
+    }
+    #[dbus_interface(signal)]
+    async fn device_added(ctxt: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+    #[dbus_interface(signal)]
+    async fn device_removed(ctxt: &SignalContext<'_>, device: ObjectPath<'_>) -> zbus::Result<()>;
+}
+
+/// Laptop battery, wireless mouse and AC adapter, plus the display device
+fn upower_devices() -> Vec<UPowerDevice> {
+    let battery = UPowerDevice {
+        name: "battery_BAT0",
+        kind: 2,
+        model: "Mock battery",
+        vendor: "Mock vendor",
+        power_supply: true,
+        is_rechargeable: true,
+        state: 2,
+        percentage: 80.0,
+        energy: 40.0,
+        energy_full: 50.0,
+        energy_full_design: 60.0,
+        energy_rate: 10.0,
+        temperature: 30.0,
+        time_to_empty: 4 * 3600,
+        time_to_full: 0,
+        capacity: 83.0,
+        serial: "BAT-0001",
+        charge_cycles: 120,
+        voltage: 12.5,
+        technology: 1,
+        warning_level: 1,
+        charge_threshold_supported: true,
+        charge_threshold_enabled: false,
+    };
+    vec![
+        UPowerDevice {
+            name: "DisplayDevice",
+            model: "",
+            vendor: "",
+            ..battery
+        },
+        battery,
+        UPowerDevice {
+            name: "mouse_dev_00",
+            kind: 5,
+            model: "Mock mouse",
+            power_supply: false,
+            percentage: 15.0,
+            energy: 0.0,
+            energy_full: 0.0,
+            energy_full_design: 0.0,
+            energy_rate: 0.0,
+            temperature: 0.0,
+            time_to_empty: 0,
+            capacity: 0.0,
+            serial: "",
+            charge_cycles: -1,
+            voltage: 0.0,
+            charge_threshold_supported: false,
+            ..battery
+        },
+        UPowerDevice {
+            name: "line_power_AC",
+            kind: 1,
+            model: "",
+            vendor: "",
+            is_rechargeable: false,
+            state: 0,
+            percentage: 0.0,
+            energy: 0.0,
+            energy_full: 0.0,
+            energy_full_design: 0.0,
+            energy_rate: 0.0,
+            temperature: 0.0,
+            time_to_empty: 0,
+            capacity: 0.0,
+            serial: "",
+            charge_cycles: -1,
+            voltage: 0.0,
+            technology: 0,
+            charge_threshold_supported: false,
+            ..battery
+        },
+    ]
+}
+
+#[derive(Clone, Copy)]
+struct UPowerDevice {
+    name: &'static str,
+    kind: u32,
+    model: &'static str,
+    vendor: &'static str,
+    power_supply: bool,
+    is_rechargeable: bool,
+    state: u32,
+    percentage: f64,
+    energy: f64,
+    energy_full: f64,
+    energy_full_design: f64,
+    energy_rate: f64,
+    temperature: f64,
+    time_to_empty: i64,
+    time_to_full: i64,
+    capacity: f64,
+    serial: &'static str,
+    charge_cycles: i32,
+    voltage: f64,
+    technology: u32,
+    warning_level: u32,
+    charge_threshold_supported: bool,
+    charge_threshold_enabled: bool,
+}
+
+impl UPowerDevice {
+    fn path(&self) -> String {
+        format!("/org/freedesktop/UPower/devices/{}", self.name)
+    }
+}
+
+#[dbus_interface(name = "org.freedesktop.UPower.Device")]
+impl UPowerDevice {
+    /// Three samples a minute apart, from 2023-11-14 22:13:20 UTC backwards
+    fn get_history(
+        &self,
+        kind: &str,
+        timespan: u32,
+        resolution: u32,
+    ) -> zbus::fdo::Result<Vec<(u32, f64, u32)>> {
+        let value = match kind {
+            "charge" => self.percentage,
+            "rate" => self.energy_rate,
+            "time-full" => self.time_to_full as f64,
+            "time-empty" => self.time_to_empty as f64,
+            _ => return Err(zbus::fdo::Error::InvalidArgs(kind.to_string())),
+        };
+        Ok((0..3)
+            .map(|index| (1_700_000_000 - index * 60, value + index as f64, self.state))
+            .filter(|(time, _, _)| 1_700_000_000 - time <= timespan)
+            .take(resolution as usize)
+            .collect())
+    }
+    /// One point per percentage, from 0 to 100
+    fn get_statistics(&self, kind: &str) -> zbus::fdo::Result<Vec<(f64, f64)>> {
+        let factor = match kind {
+            "charging" => 1.0,
+            "discharging" => 2.0,
+            _ => return Err(zbus::fdo::Error::InvalidArgs(kind.to_string())),
+        };
+        Ok((0..=100)
+            .map(|percentage| (percentage as f64 * factor, 50.0))
+            .collect())
+    }
+    #[dbus_interface(property)]
+    fn native_path(&self) -> String {
+        self.name.to_string()
+    }
+    #[dbus_interface(property, name = "Type")]
+    fn kind(&self) -> u32 {
+        self.kind
+    }
+    #[dbus_interface(property)]
+    fn model(&self) -> String {
+        self.model.to_string()
+    }
+    #[dbus_interface(property)]
+    fn vendor(&self) -> String {
+        self.vendor.to_string()
+    }
+    #[dbus_interface(property)]
+    fn power_supply(&self) -> bool {
+        self.power_supply
+    }
+    #[dbus_interface(property)]
+    fn is_rechargeable(&self) -> bool {
+        self.is_rechargeable
+    }
+    #[dbus_interface(property)]
+    fn is_present(&self) -> bool {
+        true
+    }
+    #[dbus_interface(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+    #[dbus_interface(property)]
+    fn percentage(&self) -> f64 {
+        self.percentage
+    }
+    #[dbus_interface(property)]
+    fn energy(&self) -> f64 {
+        self.energy
+    }
+    #[dbus_interface(property)]
+    fn energy_full(&self) -> f64 {
+        self.energy_full
+    }
+    #[dbus_interface(property)]
+    fn energy_full_design(&self) -> f64 {
+        self.energy_full_design
+    }
+    #[dbus_interface(property)]
+    fn energy_rate(&self) -> f64 {
+        self.energy_rate
+    }
+    #[dbus_interface(property)]
+    fn temperature(&self) -> f64 {
+        self.temperature
+    }
+    #[dbus_interface(property)]
+    fn time_to_empty(&self) -> i64 {
+        self.time_to_empty
+    }
+    #[dbus_interface(property)]
+    fn time_to_full(&self) -> i64 {
+        self.time_to_full
+    }
+    #[dbus_interface(property)]
+    fn capacity(&self) -> f64 {
+        self.capacity
+    }
+    #[dbus_interface(property)]
+    fn serial(&self) -> String {
+        self.serial.to_string()
+    }
+    #[dbus_interface(property)]
+    fn charge_cycles(&self) -> i32 {
+        self.charge_cycles
+    }
+    #[dbus_interface(property)]
+    fn voltage(&self) -> f64 {
+        self.voltage
+    }
+    #[dbus_interface(property)]
+    fn technology(&self) -> u32 {
+        self.technology
+    }
+    #[dbus_interface(property)]
+    fn warning_level(&self) -> u32 {
+        self.warning_level
+    }
+    #[dbus_interface(property)]
+    fn charge_threshold_supported(&self) -> bool {
+        self.charge_threshold_supported
+    }
+    #[dbus_interface(property)]
+    fn charge_threshold_enabled(&self) -> bool {
+        self.charge_threshold_enabled
+    }
+    #[dbus_interface(property)]
+    fn charge_start_threshold(&self) -> u32 {
+        75
+    }
+    #[dbus_interface(property)]
+    fn charge_end_threshold(&self) -> u32 {
+        80
+    }
+    async fn enable_charge_threshold(
+        &mut self,
+        enabled: bool,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> zbus::fdo::Result<()> {
+        if !self.charge_threshold_supported {
+            return Err(zbus::fdo::Error::NotSupported(
+                "Charge thresholds are not supported".to_string(),
+            ));
+        }
+        self.charge_threshold_enabled = enabled;
+        self.charge_threshold_enabled_changed(&ctxt).await?;
+        Ok(())
+    }
+}
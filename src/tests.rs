@@ -1,71 +1,282 @@
+use crate::dconf;
 use crate::handlers::easy_gnome;
 use crate::handlers::easy_gnome::battery;
 use crate::handlers::easy_gnome::extensions;
 use crate::handlers::easy_gnome::interface;
+use crate::handlers::easy_gnome::keyboard_backlight;
 use crate::handlers::easy_gnome::nightlight;
 use crate::handlers::easy_gnome::peripherals;
 use crate::handlers::easy_gnome::power;
 use crate::handlers::easy_gnome::screen;
+use crate::handlers::easy_gnome::screencast;
 use crate::handlers::easy_gnome::screenshot;
+use crate::schemas;
+use crate::test_support;
 #[test]
+#[ignore = "the icon theme needs a display, run with --ignored on a desktop"]
 fn get_all_apps() {
     gtk::init().unwrap();
-    let apps = easy_gnome::apps::Apps::new();
+    let apps = easy_gnome::apps::Apps::new().unwrap();
     assert!(apps.get_apps().len() > 0);
 }
+#[tokio::test]
 async fn pick_color() {
-    let (r, g, b) = screenshot::pick_color().await;
+    let _bus = test_support::bus().await;
+    let color = screenshot::pick_color().await.unwrap().unwrap();
+    assert_eq!(color, screenshot::Color::new(0.5, 0.25, 1.0));
+}
+#[test]
+fn color_conversions() {
+    let color = screenshot::Color::new(0.5, 0.25, 1.0);
+    assert_eq!(color.to_rgb8(), (128, 64, 255));
+    assert_eq!(color.to_hex(), "#8040ff");
+    assert_eq!(color.to_css(), "rgb(128, 64, 255)");
+    assert_eq!(color.to_css_hsl(), "hsl(260, 100%, 63%)");
+    let (hue, saturation, value) = color.to_hsv();
+    assert_eq!((hue.round(), saturation, value), (260.0, 0.75, 1.0));
+    assert_eq!(
+        screenshot::Color::from_hex("#8040ff").unwrap().to_rgb8(),
+        (128, 64, 255)
+    );
+    assert!(screenshot::Color::from_hex("#80").is_err());
+}
+#[tokio::test]
+async fn pick_cancelled() {
+    let bus = test_support::bus().await;
+    // Escape while picking is not an error
+    bus.set_pick_cancelled(true).await;
+    let color = screenshot::pick_color().await;
+    bus.set_pick_cancelled(false).await;
+    assert_eq!(color.unwrap(), None);
+    // The mock shell answers like GNOME Shell when Escape is pressed
+    let options = screenshot::ScreenshotOptions::default().output("/tmp/cancel.png");
+    let error = screenshot::capture_image(screenshot::Target::Screen, &options)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, crate::Error::Cancelled(_)));
+    assert!(error.is_cancelled());
+    let error = crate::Error::DBus {
+        name: "org.freedesktop.DBus.Error.Failed".to_string(),
+        message: "Cannot cancel: no operation".to_string(),
+    };
+    assert!(!error.is_cancelled());
+    assert!(!crate::Error::AccessDenied("Cancelled by policy".to_string()).is_cancelled());
+}
+#[tokio::test]
+async fn color_history() {
+    let _bus = test_support::bus().await;
+    let path = std::env::temp_dir().join(format!("colors-{}", std::process::id()));
+    let mut history = screenshot::ColorHistory::open(&path).unwrap().with_limit(2);
+    history.push(screenshot::Color::from_rgb8(1, 2, 3)).unwrap();
+    history.push(screenshot::Color::from_rgb8(4, 5, 6)).unwrap();
+    screenshot::pick_color_with_history(&mut history)
+        .await
+        .unwrap();
+    history.push(screenshot::Color::from_rgb8(4, 5, 6)).unwrap();
+    let hex: Vec<String> = screenshot::ColorHistory::open(&path)
+        .unwrap()
+        .colors()
+        .iter()
+        .map(|color| color.to_hex())
+        .collect();
+    assert_eq!(hex, ["#040506", "#8040ff"]);
+    std::fs::remove_file(path).unwrap();
 }
 #[tokio::test]
 async fn set_power_profile() {
+    let _bus = test_support::bus().await;
     let power_profile = easy_gnome::PowerProfile::PowerSaver;
-    power::set_power_profile(power_profile).await;
-    assert_eq!(power::get_power_profile().await, power_profile);
+    power::set_power_profile(power_profile).await.unwrap();
+    assert_eq!(power::get_power_profile().await.unwrap(), power_profile);
+
+    use futures_util::StreamExt;
+    let mut changes = power::power_profile_changes().await.unwrap();
+    power::set_power_profile(easy_gnome::PowerProfile::Balanced)
+        .await
+        .unwrap();
+    while let Some(profile) = changes.next().await {
+        if profile.unwrap() == easy_gnome::PowerProfile::Balanced {
+            break;
+        }
+    }
+}
+#[tokio::test]
+async fn power_profiles_info() {
+    let _bus = test_support::bus().await;
+    let profiles = power::get_power_profiles().await.unwrap();
+    let names: Vec<easy_gnome::PowerProfile> =
+        profiles.iter().map(|profile| profile.profile).collect();
+    assert_eq!(
+        names,
+        [
+            easy_gnome::PowerProfile::PowerSaver,
+            easy_gnome::PowerProfile::Balanced
+        ]
+    );
+    assert_eq!(profiles[0].driver, "platform_profile");
+    assert_eq!(profiles[0].cpu_driver, None);
+    assert_eq!(
+        power::performance_degraded().await.unwrap(),
+        Some(power::DegradedReason::LapDetected)
+    );
+    assert!("turbo".parse::<easy_gnome::PowerProfile>().is_err());
+}
+#[tokio::test]
+async fn hold_power_profile() {
+    let bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let hold = client
+        .hold_power_profile(
+            easy_gnome::PowerProfile::Performance,
+            "Compiling",
+            "app.test",
+        )
+        .await
+        .unwrap();
+    assert!(bus.was_called(&format!(
+        "ppd.HoldProfile(performance, Compiling, app.test) = {}",
+        hold.cookie()
+    )));
+    let released = format!("ppd.ReleaseProfile({})", hold.cookie());
+    drop(hold);
+    for _ in 0..100 {
+        if bus.was_called(&released) {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    assert!(bus.was_called(&released));
+
+    let hold = client
+        .hold_power_profile(easy_gnome::PowerProfile::PowerSaver, "Battery", "app.test")
+        .await
+        .unwrap();
+    let released = format!("ppd.ReleaseProfile({})", hold.cookie());
+    hold.release().await.unwrap();
+    assert!(bus.was_called(&released));
+    assert!(client
+        .hold_power_profile(easy_gnome::PowerProfile::Balanced, "No", "app.test")
+        .await
+        .is_err());
 }
 
 #[tokio::test]
 async fn get_extensions() {
-    let extensions = extensions::get_extensions().await;
+    let _bus = test_support::bus().await;
+    let extensions = extensions::get_extensions().await.unwrap();
     assert!(extensions.len() > 0);
     println!("{:?}", extensions);
 }
 #[tokio::test]
+async fn extension_fields() {
+    let _bus = test_support::bus().await;
+    let extensions = extensions::get_extensions().await.unwrap();
+    let find = |uuid: &str| {
+        extensions
+            .iter()
+            .find(|extension| extension.uuid == uuid)
+            .unwrap()
+            .clone()
+    };
+    let user = find("ubuntu-appindicators@ubuntu.com");
+    assert_eq!(user.version, "3");
+    assert_eq!(user.kind, Some(easy_gnome::ExtensionType::PerUser));
+    assert_eq!(user.has_prefs, Some(true));
+    // GNOME Shell sends an empty error for extensions that loaded fine
+    assert_eq!(user.error, None);
+    assert_eq!(user.can_change, Some(true));
+    assert_eq!(
+        user.shell_version,
+        Some(vec!["45".to_string(), "46".to_string()])
+    );
+    assert_eq!(
+        user.settings_schema.as_deref(),
+        Some("org.gnome.shell.extensions.mock")
+    );
+    // Listed even without name, description or url, with its unknown state
+    let broken = find("system-broken@example.com");
+    assert_eq!(broken.name, "system-broken@example.com");
+    assert_eq!(broken.url, None);
+    assert_eq!(broken.enabled, None);
+    assert_eq!(broken.kind, Some(easy_gnome::ExtensionType::System));
+    let state = broken.state.unwrap();
+    assert_eq!(state, easy_gnome::ListExtensionState::UNKNOWN(7));
+    assert_eq!(state.number(), 7);
+    assert_eq!(easy_gnome::ListExtensionState::UNINSTALLED.number(), 99);
+}
+#[tokio::test]
+async fn extension_state_changes() {
+    use futures_util::StreamExt;
+    let _bus = test_support::bus().await;
+    let uuid = "ubuntu-appindicators@ubuntu.com";
+    let mut changes = extensions::state_changes().await.unwrap();
+    let mut statuses = extensions::status_changes().await.unwrap();
+    assert!(extensions::disable_extension(uuid).await.unwrap());
+    let (_, extension) = changes.next().await.unwrap().unwrap();
+    assert_eq!(
+        extension.state,
+        Some(easy_gnome::ListExtensionState::DISABLED)
+    );
+    assert_eq!(extension.enabled, Some(false));
+    let (_, state, error) = statuses.next().await.unwrap().unwrap();
+    assert_eq!(state, easy_gnome::ListExtensionState::DISABLED);
+    assert_eq!(error, None);
+
+    assert!(extensions::enable_extension(uuid).await.unwrap());
+    let (_, extension) = changes.next().await.unwrap().unwrap();
+    assert_eq!(
+        extension.state,
+        Some(easy_gnome::ListExtensionState::ENABLED)
+    );
+}
+#[tokio::test]
 async fn launch_extension_preferences() {
-    let _extensions_list = extensions::get_extensions().await;
+    let bus = test_support::bus().await;
+    let _extensions_list = extensions::get_extensions().await.unwrap();
     // You can get the extension uuid from the extensions::get_extensions() function
     let extension_uuid = "ubuntu-appindicators@ubuntu.com";
-    extensions::open_extension_preferences(extension_uuid).await
+    extensions::open_extension_preferences(extension_uuid)
+        .await
+        .unwrap();
+    assert!(bus.was_called("extensions.LaunchExtensionPrefs(ubuntu-appindicators@ubuntu.com)"));
 }
 #[tokio::test]
 async fn disable_extension() {
-    let _extensions_list = extensions::get_extensions().await;
+    let _bus = test_support::bus().await;
+    let _extensions_list = extensions::get_extensions().await.unwrap();
     // You can get the extension uuid from the extensions::get_extensions() function
     let extension_uuid = "extension-list@tu.berry";
-    extensions::disable_extension(extension_uuid).await
+    assert!(extensions::disable_extension(extension_uuid).await.unwrap());
 }
 #[tokio::test]
 async fn enable_extension() {
-    let _extensions_list = extensions::get_extensions().await;
+    let _bus = test_support::bus().await;
+    let _extensions_list = extensions::get_extensions().await.unwrap();
     // You can get the extension uuid from the extensions::get_extensions() function
     let extension_uuid = "extension-list@tu.berry";
-    extensions::enable_extension(extension_uuid).await
+    assert!(extensions::enable_extension(extension_uuid).await.unwrap());
 }
 #[tokio::test]
 async fn uninstall_extension() {
-    let _extensions_list = extensions::get_extensions().await;
+    let _bus = test_support::bus().await;
+    let _extensions_list = extensions::get_extensions().await.unwrap();
     // You can get the extension uuid from the extensions::get_extensions() function
-    let extension_uuid = "extension-list@tu.berry";
-    extensions::uninstall_extension(extension_uuid).await
+    let extension_uuid = "uninstall-me@example.com";
+    assert!(extensions::uninstall_extension(extension_uuid)
+        .await
+        .unwrap());
 }
 
 #[test]
 fn set_keyboard_press_delay() {
+    let _settings = test_support::settings();
     let delay = 100;
     peripherals::set_keyboard_press_delay(delay).unwrap();
     assert_eq!(peripherals::get_keyboard_press_delay().unwrap(), delay);
 }
 #[test]
 fn reset_keyboard_press_delay() {
+    let _settings = test_support::settings();
     let default_delay = 500;
     peripherals::reset_keyboard_press_delay().unwrap();
     assert_eq!(
@@ -75,6 +286,7 @@ fn reset_keyboard_press_delay() {
 }
 #[test]
 fn set_keyboard_repeat_interval() {
+    let _settings = test_support::settings();
     let interval = 100;
     peripherals::set_keyboard_repeat_interval(interval).unwrap();
     assert_eq!(
@@ -84,6 +296,7 @@ fn set_keyboard_repeat_interval() {
 }
 #[test]
 fn reset_keyboard_repeat_interval() {
+    let _settings = test_support::settings();
     let default_interval = 30;
     peripherals::reset_keyboard_repeat_interval().unwrap();
     assert_eq!(
@@ -93,28 +306,26 @@ fn reset_keyboard_repeat_interval() {
 }
 #[tokio::test]
 async fn get_battery_display() {
+    let _bus = test_support::bus().await;
     let battery_display = battery::get_current_device_battery().await.unwrap();
-    let full_design_battery = battery_display.energy_full_design().await.unwrap();
-    let full_battery = battery_display.energy_full().await.unwrap();
-    let current_battery = battery_display.energy().await.unwrap();
-    let percentage = battery_display.percentage().await.unwrap();
-    let battery_state = battery_display.state().await.unwrap();
-    let temperature = battery_display.temperature().await.unwrap();
-    let is_rechargable = battery_display.is_rechargeable().await.unwrap();
-    let model = battery_display.model().await.unwrap();
-    let vendor = battery_display.vendor().await.unwrap();
-    let power_supply = battery_display.power_supply().await.unwrap();
-    let battery_type = battery_display.type_().await.unwrap();
-
-    assert!(full_design_battery > 0.0);
-    assert!(full_battery > 0.0);
-    assert!(current_battery > 0.0);
-    assert!(percentage > 0.0);
-    assert!(is_rechargable);
-    assert!(power_supply);
+    assert_eq!(battery_display.kind, battery::DeviceKind::Battery);
+    assert_eq!(battery_display.state, battery::BatteryState::Discharging);
+    assert_eq!(
+        battery_display.technology,
+        battery::BatteryTechnology::LithiumIon
+    );
+    assert_eq!(battery_display.percentage, 80.0);
+    assert_eq!(battery_display.time_to_empty, Some(4 * 3600));
+    assert_eq!(battery_display.time_to_full, None);
+    assert_eq!(battery_display.energy_full_design, 60.0);
+    assert_eq!(battery_display.capacity, 83.0);
+    assert_eq!(battery_display.charge_cycles, Some(120));
+    assert!(battery_display.is_rechargeable);
+    assert!(battery_display.power_supply);
 }
 #[test]
 fn set_show_battery_percentage() {
+    let _settings = test_support::settings();
     let show_percentage = true;
     interface::set_show_battery_percentage(show_percentage).unwrap();
     assert_eq!(
@@ -124,6 +335,7 @@ fn set_show_battery_percentage() {
 }
 #[test]
 fn reset_show_battery_percentage() {
+    let _settings = test_support::settings();
     let show_percentage = false;
     interface::reset_show_battery_percentage().unwrap();
     assert_eq!(
@@ -133,12 +345,14 @@ fn reset_show_battery_percentage() {
 }
 #[test]
 fn set_locate_pointer() {
+    let _settings = test_support::settings();
     let locate_pointer = true;
     interface::set_locate_pointer(locate_pointer).unwrap();
     assert_eq!(interface::get_locate_pointer().unwrap(), locate_pointer);
 }
 #[test]
 fn reset_locate_pointer() {
+    let _settings = test_support::settings();
     let locate_pointer = false;
     interface::reset_locate_pointer().unwrap();
     assert_eq!(interface::get_locate_pointer().unwrap(), locate_pointer);
@@ -146,124 +360,752 @@ fn reset_locate_pointer() {
 
 #[test]
 fn set_cursor_size() {
+    let _settings = test_support::settings();
     let cursor_size = 50;
     interface::set_cursor_size(cursor_size).unwrap();
     assert_eq!(interface::get_cursor_size().unwrap(), cursor_size);
 }
 #[test]
 fn reset_cursor_size() {
+    let _settings = test_support::settings();
     let cursor_size = 24;
     interface::reset_cursor_size().unwrap();
     assert_eq!(interface::get_cursor_size().unwrap(), cursor_size);
 }
 #[tokio::test]
 async fn get_devices_battery() {
+    let _bus = test_support::bus().await;
+    // The mock also lists a headset that disconnects before its properties are read
     let battery_devices = battery::get_devices_battery().await.unwrap();
-    for device in battery_devices {
-        let full_design_battery = device.energy_full_design().await.unwrap();
-        let full_battery = device.energy_full().await.unwrap();
-        let current_battery = device.energy().await.unwrap();
-        let percentage = device.percentage().await.unwrap();
-        let battery_state = device.state().await.unwrap();
-        let temperature = device.temperature().await.unwrap();
-        let is_rechargable = device.is_rechargeable().await.unwrap();
-        let model = device.model().await.unwrap();
-        let vendor = device.vendor().await.unwrap();
-        let power_supply = device.power_supply().await.unwrap();
-        let battery_type = device.type_().await.unwrap();
+    let kinds: Vec<battery::DeviceKind> =
+        battery_devices.iter().map(|device| device.kind).collect();
+    assert_eq!(
+        kinds,
+        [battery::DeviceKind::Battery, battery::DeviceKind::Mouse]
+    );
+    let mouse = &battery_devices[1];
+    assert_eq!(mouse.path, "/org/freedesktop/UPower/devices/mouse_dev_00");
+    assert_eq!(mouse.model, "Mock mouse");
+    assert_eq!(mouse.charge_cycles, None);
+    assert!(!mouse.power_supply);
+}
+#[tokio::test]
+async fn battery_history() {
+    use std::time::{Duration, UNIX_EPOCH};
+    let _bus = test_support::bus().await;
+    let path = "/org/freedesktop/UPower/devices/battery_BAT0";
+    let history = battery::get_history(
+        path,
+        battery::HistoryKind::Charge,
+        Duration::from_secs(3600),
+        2,
+    )
+    .await
+    .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(
+        history[0].time,
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    );
+    assert_eq!(history[0].value, 80.0);
+    assert_eq!(history[1].value, 81.0);
+    assert_eq!(history[1].state, battery::BatteryState::Discharging);
+    let recent = battery::get_history(
+        path,
+        battery::HistoryKind::Rate,
+        Duration::from_secs(60),
+        10,
+    )
+    .await
+    .unwrap();
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent[0].value, 10.0);
+
+    let statistics = battery::get_statistics(path, battery::StatisticsKind::Discharging)
+        .await
+        .unwrap();
+    assert_eq!(statistics.len(), 101);
+    assert_eq!(statistics[50].percentage, 50);
+    assert_eq!(statistics[50].value, 100.0);
+    assert_eq!(statistics[50].accuracy, 50.0);
+}
+#[tokio::test]
+async fn charge_limit() {
+    let _bus = test_support::bus().await;
+    let path = "/org/freedesktop/UPower/devices/battery_BAT0";
+    let limit = battery::get_charge_limit(path).await.unwrap();
+    assert!(limit.supported);
+    assert!(!limit.enabled);
+    assert_eq!((limit.start, limit.end), (75, 80));
+    battery::set_charge_limit_enabled(path, true).await.unwrap();
+    assert!(battery::get_charge_limit(path).await.unwrap().enabled);
+    battery::set_charge_limit_enabled(path, false)
+        .await
+        .unwrap();
 
-        println!(
-            "full_design_battery: {} full_battery: {} current_battery: {} percentage: {} battery_state: {:?} temperature: {} is_rechargable: {} model: {} vendor: {} power_supply: {} battery_type: {:?}",
-            full_design_battery, full_battery, current_battery, percentage, battery_state, temperature, is_rechargable, model, vendor, power_supply,battery_type
-        );
+    let mouse = "/org/freedesktop/UPower/devices/mouse_dev_00";
+    assert!(!battery::get_charge_limit(mouse).await.unwrap().supported);
+    assert!(matches!(
+        battery::set_charge_limit_enabled(mouse, true).await,
+        Err(crate::Error::Unsupported(_))
+    ));
+}
+#[tokio::test]
+async fn low_battery_events() {
+    use futures_util::StreamExt;
+    let bus = test_support::bus().await;
+    let thresholds = battery::LowBatteryThresholds::new().with(battery::DeviceKind::Mouse, 20.0);
+    let mut events = battery::low_battery_events(thresholds).await.unwrap();
+    // The mouse is already below its threshold, the laptop battery has none
+    let event = events.next().await.unwrap().unwrap();
+    assert_eq!(event.model, "Mock mouse");
+    assert_eq!(event.kind, battery::DeviceKind::Mouse);
+    assert_eq!(event.reason, battery::LowBatteryReason::Below(20.0));
+
+    bus.set_device_warning_level("battery_BAT0", 3).await;
+    let event = events.next().await.unwrap().unwrap();
+    bus.set_device_warning_level("battery_BAT0", 1).await;
+    assert_eq!(event.model, "Mock battery");
+    assert_eq!(event.kind, battery::DeviceKind::Battery);
+    assert_eq!(
+        event.reason,
+        battery::LowBatteryReason::WarningLevel(battery::WarningLevel::Low)
+    );
+}
+#[tokio::test]
+async fn low_battery_events_plugged_device() {
+    use futures_util::StreamExt;
+    let bus = test_support::bus().await;
+    let thresholds = battery::LowBatteryThresholds::new().with(battery::DeviceKind::Keyboard, 30.0);
+    let mut events = battery::low_battery_events(thresholds).await.unwrap();
+    // Plugged in after subscribing, then drained below its threshold
+    bus.plug_device("keyboard_dev_00", 6, 50.0).await;
+    bus.set_device_percentage("keyboard_dev_00", 25.0).await;
+    let event = events.next().await.unwrap().unwrap();
+    bus.unplug_device("keyboard_dev_00").await;
+    assert_eq!(
+        event.path,
+        "/org/freedesktop/UPower/devices/keyboard_dev_00"
+    );
+    assert_eq!(event.percentage, 25.0);
+    assert_eq!(event.reason, battery::LowBatteryReason::Below(30.0));
+}
+#[tokio::test]
+async fn battery_events() {
+    use futures_util::StreamExt;
+    let bus = test_support::bus().await;
+    assert!(battery::lid_is_present().await.unwrap());
+    assert!(!battery::lid_is_closed().await.unwrap());
+
+    let mut on_battery = battery::on_battery_changes().await.unwrap();
+    bus.set_on_battery(false).await;
+    while let Some(value) = on_battery.next().await {
+        if !value.unwrap() {
+            break;
+        }
     }
+    assert!(!battery::on_battery().await.unwrap());
+    bus.set_on_battery(true).await;
+
+    let mut devices = battery::device_events().await.unwrap();
+    bus.emit_device("mouse_dev_01", true).await;
+    bus.emit_device("mouse_dev_01", false).await;
+    let mut events = vec![
+        devices.next().await.unwrap().unwrap(),
+        devices.next().await.unwrap().unwrap(),
+    ];
+    events.sort_by_key(|event| matches!(event, battery::DeviceEvent::Removed(_)));
+    let path = "/org/freedesktop/UPower/devices/mouse_dev_01".to_string();
+    assert_eq!(
+        events,
+        [
+            battery::DeviceEvent::Added(path.clone()),
+            battery::DeviceEvent::Removed(path)
+        ]
+    );
+
+    let mouse = "/org/freedesktop/UPower/devices/mouse_dev_00";
+    let mut changes = battery::device_changes(mouse).await.unwrap();
+    bus.set_device_percentage("mouse_dev_00", 10.0).await;
+    let snapshot = changes.next().await.unwrap().unwrap();
+    assert_eq!(snapshot.percentage, 10.0);
+    assert_eq!(snapshot.model, "Mock mouse");
+    bus.set_device_percentage("mouse_dev_00", 15.0).await;
 }
 #[test]
 fn set_mouse_natural_scroll() {
+    let _settings = test_support::settings();
     peripherals::set_mouse_natural_scroll(true).unwrap();
     assert_eq!(peripherals::get_mouse_natural_scroll().unwrap(), true);
 }
 #[test]
 fn reset_mouse_natural_scroll() {
+    let _settings = test_support::settings();
     peripherals::reset_mouse_natural_scroll().unwrap();
     assert_eq!(peripherals::get_mouse_natural_scroll().unwrap(), false);
 }
 #[test]
 fn set_touchpad_tap_to_click() {
+    let _settings = test_support::settings();
     peripherals::set_touchpad_tap_to_click(false).unwrap();
     assert_eq!(peripherals::get_touchpad_tap_to_click().unwrap(), false);
 }
 #[test]
 fn reset_touchpad_tap_to_click() {
+    let _settings = test_support::settings();
     peripherals::reset_touchpad_tap_to_click().unwrap();
     assert_eq!(peripherals::get_touchpad_tap_to_click().unwrap(), false);
 }
 #[test]
 fn set_two_finger_scroll() {
+    let _settings = test_support::settings();
     peripherals::set_two_finger_scroll(false).unwrap();
     assert_eq!(peripherals::get_two_finger_scroll().unwrap(), false);
 }
 #[test]
 fn reset_two_finger_scroll() {
+    let _settings = test_support::settings();
     peripherals::reset_two_finger_scroll().unwrap();
     assert_eq!(peripherals::get_two_finger_scroll().unwrap(), true);
 }
 
+#[tokio::test]
 async fn power_off() {
-    power::power_off().await;
+    let bus = test_support::bus().await;
+    power::power_off().await.unwrap();
+    assert!(bus.was_called("login1.PowerOff(true)"));
 }
+#[tokio::test]
 async fn reboot() {
-    power::reboot().await;
+    let bus = test_support::bus().await;
+    power::reboot().await.unwrap();
+    assert!(bus.was_called("login1.Reboot(true)"));
 }
+#[tokio::test]
 async fn suspend() {
-    power::suspend().await;
+    let bus = test_support::bus().await;
+    power::suspend().await.unwrap();
+    assert!(bus.was_called("login1.Suspend(true)"));
 }
 
 #[test]
 fn get_temperature() {
-    let temperature: u32 = nightlight::get_temperature();
+    let _settings = test_support::settings();
+    let temperature: u32 = nightlight::get_temperature().unwrap();
     println!("temperature: {}", temperature);
     assert!(temperature > 0);
 }
 #[test]
 fn set_temperature() {
+    let _settings = test_support::settings();
     let temperature: u32 = 3000;
-    nightlight::set_temperature(temperature);
-    assert_eq!(nightlight::get_temperature(), temperature);
+    nightlight::set_temperature(temperature).unwrap();
+    assert_eq!(nightlight::get_temperature().unwrap(), temperature);
 }
 #[test]
 
 fn reset_temperature() {
+    let _settings = test_support::settings();
     let temperature: u32 = 2700;
-    nightlight::reset_temperature();
-    assert_eq!(nightlight::get_temperature(), temperature);
+    nightlight::reset_temperature().unwrap();
+    assert_eq!(nightlight::get_temperature().unwrap(), temperature);
 }
 #[test]
 fn set_nightlight_active() {
+    let _settings = test_support::settings();
     let active = true;
-    nightlight::set_nightlight_active(active);
-    assert_eq!(nightlight::get_nightlight_active(), active);
+    nightlight::set_nightlight_active(active).unwrap();
+    assert_eq!(nightlight::get_nightlight_active().unwrap(), active);
 }
 #[test]
 fn get_nightlight_active() {
+    let _settings = test_support::settings();
     let active = false;
-    nightlight::set_nightlight_active(active);
-    assert_eq!(nightlight::get_nightlight_active(), active);
+    nightlight::set_nightlight_active(active).unwrap();
+    assert_eq!(nightlight::get_nightlight_active().unwrap(), active);
+}
+
+#[tokio::test]
+async fn brightness() {
+    use futures_util::StreamExt;
+    let _bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let percent = |value| screen::Brightness::new(value).unwrap();
+    client.set_brightness(percent(50)).await.unwrap();
+    assert_eq!(client.brightness().await.unwrap(), percent(50));
+    client.brightness_step_up().await.unwrap();
+    assert_eq!(client.brightness().await.unwrap(), percent(60));
+    client.brightness_step_down().await.unwrap();
+    assert_eq!(client.brightness().await.unwrap(), percent(50));
+    screen::step_up().await.unwrap();
+    assert_eq!(screen::brightness().await.unwrap(), percent(60));
+
+    let mut changes = client.brightness_changes().await.unwrap();
+    client.set_brightness(percent(30)).await.unwrap();
+    while let Some(brightness) = changes.next().await {
+        if brightness.unwrap() == percent(30) {
+            break;
+        }
+    }
+}
+#[test]
+fn brightness_range() {
+    assert!(screen::Brightness::new(100).is_ok());
+    assert!(matches!(
+        screen::Brightness::new(101),
+        Err(crate::Error::InvalidValue(_))
+    ));
+    assert!(screen::Brightness::try_from(-5).is_err());
+    assert!(matches!(
+        screen::Brightness::from_reply(-1),
+        Err(crate::Error::Unsupported(_))
+    ));
+    assert_eq!(screen::Brightness::new(42).unwrap().to_string(), "42%");
+}
+#[test]
+fn get_typed_value() {
+    let _settings = test_support::settings();
+    let delay: u32 = dconf::get("org.gnome.desktop.peripherals.keyboard", "delay").unwrap();
+    let text = dconf::get_text("org.gnome.desktop.peripherals.keyboard", "delay").unwrap();
+    assert_eq!(text, delay.to_string());
+    let sources: Vec<(String, String)> =
+        dconf::get("org.gnome.desktop.input-sources", "sources").unwrap();
+    assert_eq!(sources, vec![("xkb".to_string(), "us".to_string())]);
+}
+#[test]
+fn set_value_with_wrong_type() {
+    let _settings = test_support::settings();
+    let result = dconf::set("org.gnome.desktop.peripherals.keyboard", "delay", true);
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+}
+#[test]
+fn get_value_from_missing_schema() {
+    let _settings = test_support::settings();
+    let result = dconf::get_value("org.example.missing", "key");
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+}
+#[test]
+fn list_schemas() {
+    let _settings = test_support::settings();
+    let schemas = schemas::list_schemas().unwrap();
+    assert!(schemas
+        .iter()
+        .any(|schema| schema.id == "org.gnome.desktop.peripherals.keyboard"));
+}
+#[test]
+fn get_key_info() {
+    let _settings = test_support::settings();
+    let keys = schemas::list_keys("org.gnome.desktop.peripherals.keyboard").unwrap();
+    assert!(keys.contains(&"delay".to_string()));
+    let info = schemas::key_info("org.gnome.desktop.peripherals.keyboard", "delay").unwrap();
+    assert_eq!(info.value_type, "u");
+    assert_eq!(info.default_value.get::<u32>(), Some(500));
+    assert_eq!(info.writable, Some(true));
 }
+#[test]
+fn watch_key() {
+    use futures_util::StreamExt;
+
+    let _settings = test_support::settings();
+    let mut changes = nightlight::watch_temperature().unwrap();
+    nightlight::set_temperature(3500).unwrap();
+    assert_eq!(zbus::block_on(changes.next()), Some(3500));
+    nightlight::reset_temperature().unwrap();
+    assert_eq!(zbus::block_on(changes.next()), Some(2700));
+}
+#[test]
+fn watch_schema() {
+    use futures_util::StreamExt;
 
-async fn brightness_up() -> Result<(), String> {
-    screen::step_up().await;
-    Ok(())
+    let _settings = test_support::settings();
+    // Other tests leave the key set, writing the same value again emits nothing
+    let natural_scroll = !peripherals::get_mouse_natural_scroll().unwrap();
+    let mut changes = dconf::watch_schema("org.gnome.desktop.peripherals.mouse").unwrap();
+    peripherals::set_mouse_natural_scroll(natural_scroll).unwrap();
+    let (key, value) = zbus::block_on(changes.next()).unwrap();
+    assert_eq!(key, "natural-scroll");
+    assert_eq!(value.get::<bool>(), Some(natural_scroll));
+    peripherals::reset_mouse_natural_scroll().unwrap();
+}
+#[test]
+fn watch_key_with_wrong_type() {
+    let _settings = test_support::settings();
+    let result = dconf::watch::<String>("org.gnome.desktop.peripherals.keyboard", "delay");
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+}
+#[tokio::test]
+async fn client_reuses_connection() {
+    let _bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let first = client.session().await.unwrap().unique_name().cloned();
+    client.extensions().await.unwrap();
+    let second = client.session().await.unwrap().unique_name().cloned();
+    assert_eq!(first, second);
+}
+#[test]
+fn relocatable_schema_needs_path() {
+    let _settings = test_support::settings();
+    let schema = "org.gnome.settings-daemon.plugins.media-keys.custom-keybinding";
+    let result = dconf::get::<String>(schema, "name");
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+    let with_path = format!("{}:/org/gnome/settings-daemon/custom0/", schema);
+    dconf::set(&with_path, "name", "Terminal").unwrap();
+    assert_eq!(
+        dconf::get::<String>(&with_path, "name").unwrap(),
+        "Terminal"
+    );
+    // Checked before GIO sees them, it aborts on both
+    let malformed = format!("{}:org/gnome/settings-daemon/custom0", schema);
+    let result = dconf::get::<String>(&malformed, "name");
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+    let result = dconf::get::<u32>("org.gnome.desktop.peripherals.keyboard:/custom/", "delay");
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+}
+#[test]
+fn set_value_out_of_range() {
+    let _settings = test_support::settings();
+    let result = nightlight::set_temperature(100);
+    assert!(matches!(result, Err(crate::Error::InvalidValue(_))));
+}
+#[test]
+fn get_enum_key_info() {
+    let _settings = test_support::settings();
+    let info = schemas::key_info("org.gnome.desktop.interface", "color-scheme").unwrap();
+    match info.range {
+        schemas::KeyRange::Enum(choices) => {
+            assert_eq!(choices, vec!["default", "prefer-dark", "prefer-light"])
+        }
+        range => panic!("Unexpected range {:?}", range),
+    }
+}
+#[tokio::test]
+async fn screenshot_to_file() {
+    let bus = test_support::bus().await;
+    let output = std::env::temp_dir().join(format!("screenshot-{}.png", std::process::id()));
+    let options = screenshot::ScreenshotOptions::new()
+        .include_cursor(true)
+        .output(&output);
+    let path = screenshot::screenshot(&options).await.unwrap();
+    assert_eq!(path, output);
+    assert!(path.exists());
+    assert!(bus.was_called("screenshot.Screenshot(true, false)"));
+    std::fs::remove_file(path).unwrap();
+}
+#[tokio::test]
+async fn capture_selected_area() {
+    let bus = test_support::bus().await;
+    let area = screenshot::select_area().await.unwrap();
+    assert_eq!(
+        area,
+        screenshot::Area {
+            x: 10,
+            y: 20,
+            width: 30,
+            height: 40
+        }
+    );
+    let options = screenshot::ScreenshotOptions::new().flash(true);
+    let image = screenshot::capture_image(screenshot::Target::Area(area), &options)
+        .await
+        .unwrap();
+    assert_eq!(image.dimensions(), (30, 40));
+    screenshot::flash_area(area).await.unwrap();
+    assert!(bus.was_called("screenshot.FlashArea(10, 20, 30, 40)"));
 }
-async fn brightness_down() -> Result<(), String> {
-    screen::step_down().await;
-    Ok(())
+#[tokio::test]
+async fn capture_window() {
+    let _bus = test_support::bus().await;
+    let target = screenshot::Target::Window {
+        include_frame: true,
+    };
+    let image = screenshot::capture_image(target, &screenshot::ScreenshotOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(image.dimensions(), (32, 24));
 }
-async fn get_brightness() -> Result<i32, String> {
-    let brightness = screen::brightness().await;
-    Ok(brightness)
+#[tokio::test]
+async fn screencast_toggle() {
+    let bus = test_support::bus().await;
+    let client = test_support::client().await;
+    assert!(!client.is_recording().await.unwrap());
+    assert_eq!(client.stop_screencast().await.unwrap(), None);
+    let options = screencast::ScreencastOptions::new()
+        .file_template("Recording %d")
+        .framerate(60)
+        .draw_cursor(false);
+    let path = client.start_screencast(&options).await.unwrap();
+    assert_eq!(
+        path,
+        std::path::PathBuf::from("/home/user/Videos/Recording 2024-01-01.webm")
+    );
+    assert!(bus.was_called("screencast.Screencast(draw-cursor, framerate)"));
+    assert!(client.is_recording().await.unwrap());
+    assert!(client
+        .start_screencast(&screencast::ScreencastOptions::new())
+        .await
+        .is_err());
+    assert_eq!(client.stop_screencast().await.unwrap(), Some(path));
+    assert!(!client.is_recording().await.unwrap());
+    assert!(client
+        .start_screencast(&screencast::ScreencastOptions::new().framerate(0))
+        .await
+        .is_err());
 }
-async fn set_brightness(value: i32) -> Result<(), String> {
-    screen::set_brightness(value).await;
-    Ok(())
+#[tokio::test]
+async fn screencast_service_gone() {
+    let session = test_support::new_bus();
+    let service = test_support::serve_screencast(&session.address).await;
+    let client = test_support::client_with_session_bus(&session.address).await;
+    let path = client
+        .start_screencast(&screencast::ScreencastOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(client.recording().await.unwrap(), Some(path));
+    // Like a shell restart: the service that ran the recording leaves the bus
+    drop(service);
+    let mut attempts = 0;
+    while client.is_recording().await.unwrap() {
+        attempts += 1;
+        assert!(attempts < 100, "the recording outlived its service");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(client.stop_screencast().await.unwrap(), None);
+}
+#[tokio::test]
+async fn keyboard_backlight_missing() {
+    let session = test_support::new_bus();
+    let system = test_support::new_bus();
+    let _upower = test_support::serve_kbd_backlight(&system.address, 0).await;
+    let client = test_support::client_with_buses(&session.address, &system.address).await;
+    assert_eq!(
+        client.keyboard_backlight_backend().await.unwrap(),
+        keyboard_backlight::Backend::UPower
+    );
+    let brightness = screen::Brightness::new(50).unwrap();
+    assert!(matches!(
+        client.set_keyboard_brightness(brightness).await,
+        Err(crate::Error::Unsupported(_))
+    ));
+    assert!(matches!(
+        client.keyboard_brightness_step_up().await,
+        Err(crate::Error::Unsupported(_))
+    ));
+    assert!(matches!(
+        client.toggle_keyboard_backlight().await,
+        Err(crate::Error::Unsupported(_))
+    ));
+
+    // gnome-settings-daemon starting later takes over
+    let _settings_daemon = test_support::serve_settings_daemon_keyboard(&session.address).await;
+    let mut attempts = 0;
+    while client.keyboard_backlight_backend().await.unwrap()
+        != keyboard_backlight::Backend::SettingsDaemon
+    {
+        attempts += 1;
+        assert!(attempts < 100, "the backend was not detected again");
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(
+        client.keyboard_brightness().await.unwrap(),
+        screen::Brightness::new(40).unwrap()
+    );
+}
+#[tokio::test]
+async fn keyboard_backlight_settings_daemon() {
+    use futures_util::StreamExt;
+    let _bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let percent = |value| screen::Brightness::new(value).unwrap();
+    assert_eq!(
+        client.keyboard_backlight_backend().await.unwrap(),
+        keyboard_backlight::Backend::SettingsDaemon
+    );
+    client.set_keyboard_brightness(percent(40)).await.unwrap();
+    assert_eq!(client.keyboard_brightness().await.unwrap(), percent(40));
+    assert_eq!(
+        client.keyboard_brightness_step_up().await.unwrap(),
+        percent(50)
+    );
+    assert_eq!(
+        client.toggle_keyboard_backlight().await.unwrap(),
+        percent(0)
+    );
+    assert_eq!(
+        client.toggle_keyboard_backlight().await.unwrap(),
+        percent(50)
+    );
+    let mut changes = client.keyboard_brightness_changes().await.unwrap();
+    client.keyboard_brightness_step_down().await.unwrap();
+    assert_eq!(changes.next().await.unwrap().unwrap(), percent(40));
+}
+#[tokio::test]
+async fn keyboard_backlight_upower_fallback() {
+    use futures_util::StreamExt;
+    let _bus = test_support::bus().await;
+    let client = test_support::client_without_session_services().await;
+    let percent = |value| screen::Brightness::new(value).unwrap();
+    assert_eq!(
+        client.keyboard_backlight_backend().await.unwrap(),
+        keyboard_backlight::Backend::UPower
+    );
+    client.set_keyboard_brightness(percent(30)).await.unwrap();
+    assert_eq!(client.keyboard_brightness().await.unwrap(), percent(33));
+    assert_eq!(
+        client.keyboard_brightness_step_up().await.unwrap(),
+        percent(67)
+    );
+    assert_eq!(
+        client.keyboard_brightness_step_up().await.unwrap(),
+        percent(100)
+    );
+    assert_eq!(
+        client.keyboard_brightness_step_up().await.unwrap(),
+        percent(100)
+    );
+    assert_eq!(
+        client.toggle_keyboard_backlight().await.unwrap(),
+        percent(0)
+    );
+    let mut changes = client.keyboard_brightness_changes().await.unwrap();
+    client.toggle_keyboard_backlight().await.unwrap();
+    assert_eq!(changes.next().await.unwrap().unwrap(), percent(100));
+}
+#[tokio::test]
+async fn power_capabilities() {
+    use power::{Capability, PowerAction};
+    let _bus = test_support::bus().await;
+    assert_eq!(
+        power::can(PowerAction::PowerOff).await.unwrap(),
+        Capability::Yes
+    );
+    let capabilities = power::capabilities().await.unwrap();
+    assert_eq!(capabilities.len(), PowerAction::ALL.len());
+    assert!(capabilities.contains(&(PowerAction::Hibernate, Capability::NotApplicable)));
+    assert!(capabilities.contains(&(PowerAction::HybridSleep, Capability::Challenge)));
+    assert!(!Capability::No.is_available());
+    assert!("maybe".parse::<Capability>().is_err());
+}
+#[tokio::test]
+async fn power_actions() {
+    use power::PowerAction;
+    let bus = test_support::bus().await;
+    assert!(matches!(
+        power::perform(PowerAction::HybridSleep, false).await,
+        Err(crate::Error::AccessDenied(_))
+    ));
+    power::perform(PowerAction::HybridSleep, true)
+        .await
+        .unwrap();
+    assert!(bus.was_called("login1.HybridSleep(true)"));
+    power::suspend_then_hibernate().await.unwrap();
+    assert!(bus.was_called("login1.SuspendThenHibernate(true)"));
+    assert!(power::hibernate().await.is_err());
+}
+#[tokio::test]
+async fn inhibit_sleep() {
+    use power::{InhibitMode, InhibitWhat};
+    let _bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let is_listed = |inhibitors: Vec<power::Inhibitor>| {
+        inhibitors
+            .iter()
+            .any(|inhibitor| inhibitor.why == "Exporting video")
+    };
+    let guard = client
+        .inhibit(
+            &[InhibitWhat::Sleep, InhibitWhat::Shutdown],
+            "Video editor",
+            "Exporting video",
+            InhibitMode::Block,
+        )
+        .await
+        .unwrap();
+    assert_eq!(guard.what(), [InhibitWhat::Sleep, InhibitWhat::Shutdown]);
+    let inhibitors = client.list_inhibitors().await.unwrap();
+    let inhibitor = inhibitors
+        .iter()
+        .find(|inhibitor| inhibitor.why == "Exporting video")
+        .unwrap();
+    assert_eq!(inhibitor.what, [InhibitWhat::Sleep, InhibitWhat::Shutdown]);
+    assert_eq!(inhibitor.who, "Video editor");
+    assert_eq!(inhibitor.mode, InhibitMode::Block);
+    // An inhibitor from a newer logind does not break the listing
+    let _future = client
+        .power_management_proxy()
+        .await
+        .unwrap()
+        .Inhibit("sleep", "Future app", "Newer mode", "block-future")
+        .await
+        .unwrap();
+    let inhibitors = client.list_inhibitors().await.unwrap();
+    assert!(is_listed(inhibitors.clone()));
+    assert!(!inhibitors
+        .iter()
+        .any(|inhibitor| inhibitor.why == "Newer mode"));
+    guard.release();
+    assert!(!is_listed(client.list_inhibitors().await.unwrap()));
+    assert!(client
+        .inhibit(&[], "Video editor", "Nothing", InhibitMode::Delay)
+        .await
+        .is_err());
+}
+#[tokio::test]
+async fn scheduled_shutdown() {
+    use power::ShutdownKind;
+    use std::time::Duration;
+    let _bus = test_support::bus().await;
+    let client = test_support::client().await;
+    let handle = client
+        .schedule_shutdown_in(ShutdownKind::Reboot, Duration::from_secs(30 * 60))
+        .await
+        .unwrap();
+    assert!(handle.remaining() > Duration::from_secs(29 * 60));
+    assert!(handle.is_pending().await.unwrap());
+    let scheduled = client.scheduled_shutdown().await.unwrap().unwrap();
+    assert_eq!(scheduled, handle.scheduled());
+    assert_eq!(scheduled.kind, ShutdownKind::Reboot);
+    assert!(handle.cancel().await.unwrap());
+    assert_eq!(client.scheduled_shutdown().await.unwrap(), None);
+    assert!(!handle.cancel().await.unwrap());
+    assert!(!client.cancel_scheduled_shutdown().await.unwrap());
+    assert_eq!(
+        "dry-poweroff".parse::<ShutdownKind>().unwrap(),
+        ShutdownKind::PowerOff
+    );
+}
+#[tokio::test]
+async fn power_profiles_prefers_new_service() {
+    let _bus = test_support::bus().await;
+    let service = power::power_profiles_service().await.unwrap();
+    assert_eq!(service.bus_name, "org.freedesktop.UPower.PowerProfiles");
+    assert!(!service.is_legacy());
+    assert_eq!(
+        service.provider,
+        power::PowerProfilesProvider::PowerProfilesDaemon
+    );
+}
+#[tokio::test]
+async fn power_profiles_legacy_service() {
+    let system = test_support::new_bus();
+    let _service = test_support::serve_legacy_power_profiles(&system.address, true).await;
+    let client = test_support::client_with_system_bus(&system.address).await;
+    let service = client.power_profiles_service().await.unwrap();
+    assert!(service.is_legacy());
+    assert_eq!(service.path, "/net/hadess/PowerProfiles");
+    assert_eq!(service.provider, power::PowerProfilesProvider::TunedPpd);
+    assert_eq!(
+        client.power_profile().await.unwrap(),
+        easy_gnome::PowerProfile::PowerSaver
+    );
+}
+#[tokio::test]
+async fn power_profiles_missing_service() {
+    let system = test_support::new_bus();
+    let client = test_support::client_with_system_bus(&system.address).await;
+    match client.power_profile().await {
+        Err(crate::Error::ServiceUnavailable(message)) => {
+            assert!(message.contains("net.hadess.PowerProfiles"))
+        }
+        result => panic!("Expected ServiceUnavailable, got {:?}", result),
+    }
 }